@@ -2,15 +2,123 @@ use soup::prelude::*;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::prelude::*;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::Duration;
 
 extern crate reqwest;
 extern crate soup;
 
 const MAX_THREADS: usize = 4;
 const MAX_NAME_LENGTH: usize = 64;
+/// Cells in a row of a wiki armor table: the name, the 13 stats and the weight.
+const ARMOR_COLUMNS: usize = 15;
+/// Bump when `try_pieces_from_text` changes what it makes of a page, so cached pieces are
+/// parsed again.
+const PARSER_VERSION: u32 = 2;
+/// First word of a parsed pieces file, followed by the parser version and source hash.
+const PARSED_HEADER: &str = "elden_boc-pieces";
+/// Fewest pieces a slot's wiki page lists. Fewer means the page is something else, or broken.
+const MIN_PAGE_PIECES: usize = 20;
+
+/// Names of the stats, in the order used by `maximize_stat` and `stat()`. The last few are
+/// composites of the others, see `composite_total()`.
+pub const STATS: [&str; 17] = [
+    "physical",
+    "strike",
+    "slash",
+    "pierce",
+    "magic",
+    "fire",
+    "lightning",
+    "holy",
+    "immunity",
+    "robustness",
+    "focus",
+    "vitality",
+    "poise",
+    "physical average",
+    "elemental average",
+    "negation average",
+    "total resistances",
+];
+
+/// The physical damage types: physical, strike, slash and pierce.
+pub const PHYSICAL_STATS: [usize; 4] = [0, 1, 2, 3];
+
+/// The elemental damage types: magic, fire, lightning and holy.
+pub const ELEMENTAL_STATS: [usize; 4] = [4, 5, 6, 7];
+
+/// The status resistances: immunity, robustness, focus and vitality.
+pub const RESISTANCE_STATS: [usize; 4] = [8, 9, 10, 11];
+
+/// Status effects and the index in `STATS` of the resistance that builds up against them.
+pub const STATUSES: [(&str, usize); 7] = [
+    ("poison", 8),
+    ("scarlet rot", 8),
+    ("hemorrhage", 9),
+    ("frostbite", 9),
+    ("sleep", 10),
+    ("madness", 10),
+    ("death blight", 11),
+];
+
+/// Damage mixes and statuses of major bosses, see `bosses()`.
+const BOSSES: &str = include_str!("../data/bosses.txt");
+
+/// Names of Shadow of the Erdtree pieces, see `is_dlc()`.
+const DLC_PIECES: &str = include_str!("../data/dlc_pieces.txt");
+
+/// Pieces that can't be obtained in game, see `unobtainable()`.
+const UNOBTAINABLE: &str = include_str!("../data/unobtainable.txt");
+
+/// Version of the unobtainable list's format this parser reads.
+const UNOBTAINABLE_VERSION: u32 = 1;
+
+/// Number of base stats the stat at `index` in `STATS` is an average of, or 1 if it isn't one.
+#[must_use]
+pub const fn stat_divisor(index: usize) -> u16 {
+    match index {
+        13 | 14 => 4,
+        15 => 8,
+        _ => 1,
+    }
+}
+
+/// Sum of the base stats in `stats` that the composite stat at `index` in `STATS` is built from.
+/// Averages aren't divided yet, so the sum adds up exactly over pieces.
+fn composite_total(index: usize, stats: &[u16; 13]) -> u16 {
+    let total = |group: &[usize]| group.iter().map(|&x| stats[x]).sum::<u16>();
+    match index {
+        13 => total(&PHYSICAL_STATS),
+        14 => total(&ELEMENTAL_STATS),
+        15 => total(&[PHYSICAL_STATS, ELEMENTAL_STATS].concat()),
+        16 => total(&RESISTANCE_STATS),
+        _ => 0,
+    }
+}
+
+/// Value of the stat at `index` in `STATS` given the base stats `stats`, or 0 for an unknown
+/// index.
+fn stat_of(stats: &[u16; 13], index: usize) -> u16 {
+    if index < 13 {
+        stats[index]
+    } else {
+        let divisor = stat_divisor(index);
+        (composite_total(index, stats) + divisor / 2) / divisor
+    }
+}
+
+/// Like `stat_of()`, but averages are left as the sum of the stats they average, which adds up
+/// over pieces without rounding.
+fn total_of(stats: &[u16; 13], index: usize) -> u16 {
+    if index < 13 {
+        stats[index]
+    } else {
+        composite_total(index, stats)
+    }
+}
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Slot {
@@ -25,6 +133,10 @@ pub enum Slot {
 pub struct ArmorPiece {
     pub name: String,
     pub slot: Slot,
+    /// Heading of the wiki section the piece is listed in, empty if there is none.
+    pub section: String,
+    /// Whether the piece comes with Shadow of the Erdtree rather than the base game.
+    pub dlc: bool,
     pub physical: u16,
     pub strike: u16,
     pub slash: u16,
@@ -39,6 +151,7 @@ pub struct ArmorPiece {
     pub vitality: u16,
     pub poise: u16,
     pub weight: u16,
+    /// The stat being maximized, as given by `total()` so that it adds up over pieces.
     pub maximize_stat: u16,
 }
 
@@ -48,6 +161,8 @@ impl ArmorPiece {
         Self {
             name: String::with_capacity(MAX_NAME_LENGTH),
             slot,
+            section: String::new(),
+            dlc: false,
             physical: 0,
             strike: 0,
             slash: 0,
@@ -65,6 +180,38 @@ impl ArmorPiece {
             maximize_stat: 0,
         }
     }
+
+    /// The base stats, in the order of `STATS`.
+    fn base_stats(&self) -> [u16; 13] {
+        [
+            self.physical,
+            self.strike,
+            self.slash,
+            self.pierce,
+            self.magic,
+            self.fire,
+            self.lightning,
+            self.holy,
+            self.immunity,
+            self.robustness,
+            self.focus,
+            self.vitality,
+            self.poise,
+        ]
+    }
+
+    /// Value of the stat at `index` in `STATS`, or 0 for an unknown index.
+    #[must_use]
+    pub fn stat(&self, index: usize) -> u16 {
+        stat_of(&self.base_stats(), index)
+    }
+
+    /// Like `stat()`, but averages are left as the sum of the stats they average, which adds up
+    /// over pieces without rounding.
+    #[must_use]
+    pub fn total(&self, index: usize) -> u16 {
+        total_of(&self.base_stats(), index)
+    }
 }
 
 impl std::fmt::Display for Slot {
@@ -93,6 +240,7 @@ pub struct ArmorSet {
     pub vitality: u16,
     pub poise: u16,
     pub weight: u16,
+    /// The stat being maximized, as given by `total()` so that it adds up over pieces.
     pub maximize_stat: u16,
 }
 
@@ -168,6 +316,113 @@ impl ArmorSet {
                 + leg.maximize_stat,
         }
     }
+
+    /// The base stats, in the order of `STATS`.
+    fn base_stats(&self) -> [u16; 13] {
+        [
+            self.physical,
+            self.strike,
+            self.slash,
+            self.pierce,
+            self.magic,
+            self.fire,
+            self.lightning,
+            self.holy,
+            self.immunity,
+            self.robustness,
+            self.focus,
+            self.vitality,
+            self.poise,
+        ]
+    }
+
+    /// Value of the stat at `index` in `STATS`, or 0 for an unknown index.
+    #[must_use]
+    pub fn stat(&self, index: usize) -> u16 {
+        stat_of(&self.base_stats(), index)
+    }
+
+    /// Like `stat()`, but averages are left as the sum of the stats they average, which adds up
+    /// over pieces without rounding.
+    #[must_use]
+    pub fn total(&self, index: usize) -> u16 {
+        total_of(&self.base_stats(), index)
+    }
+
+    /// The set's stats with the bonuses of `talismans`, indexes into `TALISMANS`. Negation
+    /// talismans let through a share of what the armor lets through, like buffs. Names, weight
+    /// and `maximize_stat` stay those of the armor alone.
+    #[must_use]
+    pub fn with_talismans(&self, talismans: &[usize]) -> Self {
+        let mut result = self.with_damage_left(talisman_damage(talismans));
+        let mut poise: u32 = 1000;
+        for talisman in talismans.iter().map(|x| &TALISMANS[*x]) {
+            result.immunity += talisman.stats[8];
+            result.robustness += talisman.stats[9];
+            result.focus += talisman.stats[10];
+            result.vitality += talisman.stats[11];
+            result.poise += talisman.stats[12];
+            poise += u32::from(talisman.poise);
+        }
+        result.poise = (u32::from(result.poise) * poise / 1000) as u16;
+        result
+    }
+
+    /// The set's negation on top of `buffs`, indexes into `BUFFS`. Each buff lets through a share
+    /// of what the armor lets through.
+    #[must_use]
+    pub fn with_buffs(&self, buffs: &[usize]) -> Self {
+        self.with_damage_left(buff_damage(buffs))
+    }
+
+    /// The set's negation when only the share `damage` of each damage type the armor lets through
+    /// gets through.
+    fn with_damage_left(&self, damage: [f32; 8]) -> Self {
+        let mut result = self.clone();
+        let buffed = |negation: u16, index: usize| negation_with(negation, damage[index]);
+        result.physical = buffed(self.physical, 0);
+        result.strike = buffed(self.strike, 1);
+        result.slash = buffed(self.slash, 2);
+        result.pierce = buffed(self.pierce, 3);
+        result.magic = buffed(self.magic, 4);
+        result.fire = buffed(self.fire, 5);
+        result.lightning = buffed(self.lightning, 6);
+        result.holy = buffed(self.holy, 7);
+        result
+    }
+
+    /// The set's resistances with the character's own on top, as the game shows them.
+    #[must_use]
+    pub fn with_character(&self, character: &Character) -> Self {
+        let mut result = self.clone();
+        let [immunity, robustness, focus, vitality] = character.resistances();
+        result.immunity += immunity;
+        result.robustness += robustness;
+        result.focus += focus;
+        result.vitality += vitality;
+        result
+    }
+}
+
+/// Two sets' `Display` output next to each other, under `left_title` and `right_title`.
+#[must_use]
+pub fn side_by_side(
+    left: &ArmorSet,
+    right: &ArmorSet,
+    left_title: &str,
+    right_title: &str,
+) -> String {
+    let left = format!("{left_title}\n{left}");
+    let right = format!("{right_title}\n{right}");
+    let width = left.lines().map(str::len).max().unwrap_or(0) + 4;
+    let mut result = String::new();
+    let mut right_lines = right.lines();
+    for line in left.lines() {
+        let other = right_lines.next().unwrap_or("");
+        result.push_str(format!("{line:<width$}{other}").trim_end());
+        result.push('\n');
+    }
+    result
 }
 
 impl Default for ArmorSet {
@@ -198,7 +453,12 @@ impl std::fmt::Display for ArmorSet {
             robustness: {}\n\
             focus:      {}\n\
             vitality:   {}\n\
-            poise:      {}\n",
+            poise:      {}\n\
+            \n\
+            physical average:  {}\n\
+            elemental average: {}\n\
+            negation average:  {}\n\
+            total resistances: {}\n",
             self.chest,
             self.helm,
             self.gauntlets,
@@ -217,233 +477,2657 @@ impl std::fmt::Display for ArmorSet {
             f32::from(self.focus) / 10.0,
             f32::from(self.vitality) / 10.0,
             f32::from(self.poise) / 10.0,
+            f32::from(self.stat(13)) / 10.0,
+            f32::from(self.stat(14)) / 10.0,
+            f32::from(self.stat(15)) / 10.0,
+            f32::from(self.stat(16)) / 10.0,
         )
     }
 }
 
-#[must_use]
-pub fn get_set(weight_restriction: u16, pieces: Vec<ArmorPiece>) -> ArmorSet {
-    let (helms, rest): (Vec<ArmorPiece>, Vec<ArmorPiece>) =
-        pieces.into_iter().partition(|x| x.slot == Slot::Helm);
-    let (chests, rest): (Vec<ArmorPiece>, Vec<ArmorPiece>) =
-        rest.into_iter().partition(|x| x.slot == Slot::Chest);
-    let (gauntlets, legs): (Vec<ArmorPiece>, Vec<ArmorPiece>) =
-        rest.into_iter().partition(|x| x.slot == Slot::Gauntlets);
-
-    println!("Finding the best set...");
-
-    // Helmets are used for the outer loop. Break helmets into as many chunks as we have
-    // MAX_THREADS. Make sure to add the remainder so we don't do an extra loop with
-    // thread MAX_THREADS + 1.
-    let chunk_size = helms.len() / MAX_THREADS + (helms.len() % MAX_THREADS);
-    let final_result = Arc::new(Mutex::new(ArmorSet::new()));
-    let mut threads = vec![];
-    for chunk in helms.chunks(chunk_size) {
-        let final_result_clone = Arc::clone(&final_result);
-        let chests_clone = chests.clone();
-        let gauntlets_clone = gauntlets.clone();
-        let legs_clone = legs.clone();
-        let mut result = ArmorSet::new();
-        let chunk = chunk.to_owned();
-        let handle = thread::spawn(move || {
-            for helm in chunk {
-                for chest in &chests_clone {
-                    for gauntlet in &gauntlets_clone {
-                        for leg in &legs_clone {
-                            // Don't allocate an ArmorSet if this gear is too heavy.
-                            let potential_weight =
-                                helm.weight + chest.weight + gauntlet.weight + leg.weight;
+/// A temporary damage negation buff, like Boiled Crab or Opaline Hardtear.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Buff {
+    pub name: &'static str,
+    /// Buffs in the same group replace each other, so only one of them can be active.
+    pub group: &'static str,
+    /// Negation of each damage type in tenths of a percent, in the order of the first eight
+    /// `STATS`. It multiplies with the armor's negation rather than adding to it.
+    pub negation: [u16; 8],
+}
 
-                            if potential_weight > weight_restriction {
-                                continue;
-                            }
-                            // Don't allocate an ArmorSet if result.maximize_stat is better than the
-                            // potential stat.
-                            let potential_maximize_stat = helm.maximize_stat
-                                + chest.maximize_stat
-                                + gauntlet.maximize_stat
-                                + leg.maximize_stat;
+/// Buffs that raise damage negation.
+pub const BUFFS: [Buff; 9] = [
+    Buff {
+        name: "boiled crab",
+        group: "boiled",
+        negation: [100, 100, 100, 100, 0, 0, 0, 0],
+    },
+    Buff {
+        name: "boiled prawn",
+        group: "boiled",
+        negation: [100, 100, 100, 100, 0, 0, 0, 0],
+    },
+    Buff {
+        name: "opaline hardtear",
+        group: "opaline",
+        negation: [150, 150, 150, 150, 150, 150, 150, 150],
+    },
+    Buff {
+        name: "opaline bubbletear (next hit only)",
+        group: "opaline",
+        negation: [900, 900, 900, 900, 900, 900, 900, 900],
+    },
+    Buff {
+        name: "golden vow",
+        group: "golden vow",
+        negation: [100, 100, 100, 100, 100, 100, 100, 100],
+    },
+    Buff {
+        name: "magic fortification",
+        group: "magic fortification",
+        negation: [0, 0, 0, 0, 300, 0, 0, 0],
+    },
+    Buff {
+        name: "flame fortification",
+        group: "flame fortification",
+        negation: [0, 0, 0, 0, 0, 300, 0, 0],
+    },
+    Buff {
+        name: "lightning fortification",
+        group: "lightning fortification",
+        negation: [0, 0, 0, 0, 0, 0, 300, 0],
+    },
+    Buff {
+        name: "divine fortification",
+        group: "divine fortification",
+        negation: [0, 0, 0, 0, 0, 0, 0, 300],
+    },
+];
 
-                            if result.maximize_stat >= potential_maximize_stat {
-                                continue;
-                            }
+/// Negation when only the share `damage` of what `negation` lets through gets through.
+fn negation_with(negation: u16, damage: f32) -> u16 {
+    let taken = (1.0 - f32::from(negation) / 1000.0) * damage;
+    ((1.0 - taken) * 1000.0).round() as u16
+}
 
-                            // Don't allocate an ArmorSet if the maximize_stats are the same, but the
-                            // weight is not _strictly_ better. We can avoid a few more allocations if we
-                            // don't re-assign the result when there's ties, so don't consider equal weight
-                            // a contender.
-                            if result.maximize_stat == potential_maximize_stat
-                                && result.weight < potential_weight
-                            {
-                                continue;
-                            }
+/// Fraction of each damage type that gets through all of `negations`, each in the order of the
+/// first eight `STATS`.
+fn damage_left<'a>(negations: impl Iterator<Item = &'a [u16]>) -> [f32; 8] {
+    let mut damage = [1.0; 8];
+    for negation in negations {
+        for (damage, negation) in damage.iter_mut().zip(negation) {
+            *damage *= 1.0 - f32::from(*negation) / 1000.0;
+        }
+    }
+    damage
+}
 
-                            // We found one that is strictly better. Replace thread-local result
-                            // with the new set.
-                            result = ArmorSet::from(
-                                helm.clone(),
-                                chest.clone(),
-                                gauntlet.clone(),
-                                leg.clone(),
-                            );
-                        }
-                    }
-                }
-            }
-            // At the end of the loop, result stores the best possible armor set using the helmets
-            // available to that chunk. See if result is better than any thread's result. If so,
-            // replace it.
-            let mut guard = final_result_clone.lock().unwrap();
-            if result.maximize_stat > guard.maximize_stat {
-                *guard = result;
+/// Check that no two of `buffs`, indexes into `BUFFS`, replace each other.
+///
+/// # Errors
+///
+/// Returns a message naming two buffs from the same group.
+pub fn check_buffs(buffs: &[usize]) -> Result<(), String> {
+    for (position, first) in buffs.iter().enumerate() {
+        for second in &buffs[position + 1..] {
+            if first != second && BUFFS[*first].group == BUFFS[*second].group {
+                return Err(format!(
+                    "{} and {} can't be active together",
+                    BUFFS[*first].name, BUFFS[*second].name
+                ));
             }
-        });
-        threads.push(handle);
-    }
-    for handle in threads {
-        handle.join().unwrap();
+        }
     }
-    let final_result = final_result.lock().unwrap();
-    final_result.clone()
+    Ok(())
 }
 
-#[must_use]
-pub fn get_pieces_from_text(slot: &Slot, text: &str, maximize_stat: usize) -> Vec<ArmorPiece> {
-    let mut pieces: Vec<ArmorPiece> = Vec::new();
-    let soup = Soup::new(text);
-    let table = soup.tag("table").find().expect("no table");
-    let tbody = table.tag("tbody").find().expect("no tbody");
-    for row in tbody.tag("tr").find_all() {
-        let mut piece = ArmorPiece::new(Slot::Empty);
-        piece.slot = slot.clone();
-        for (i, td) in row.tag("td").find_all().enumerate() {
-            if i > 14 {
-                break;
-            }
+/// Fraction of each damage type that gets through `buffs`, indexes into `BUFFS`.
+fn buff_damage(buffs: &[usize]) -> [f32; 8] {
+    damage_left(buffs.iter().map(|x| &BUFFS[*x].negation[..]))
+}
 
-            let text = td.text().trim().to_string();
-            let mut num: u16 = 0;
-            if i != 0 {
-                let mut parse_result: f32;
-                parse_result = if let Ok(value) = text.parse::<f32>() {
-                    value
-                } else {
-                    // println!("Couldn't convert {} to f32, skipping {}", text, piece.name);
-                    continue;
-                };
+/// Fraction of each damage type that gets through `talismans`, indexes into `TALISMANS`.
+fn talisman_damage(talismans: &[usize]) -> [f32; 8] {
+    damage_left(talismans.iter().map(|x| &TALISMANS[*x].stats[..8]))
+}
 
-                if parse_result < 0.0 {
-                    // Ignore scarabs or other pieces with negative values.
-                    // println!("parse_result was {parse_result}, expected non-zero positive! {}", piece.name);
-                    continue;
-                }
-                parse_result *= 10.0;
-                // Pieces only ever have one significant digit,
-                // so truncating is fine here.
-                num = parse_result as u16;
-            }
+/// Parse amounts per damage type like `slash=60 holy=30 fire=10`, in the order of the first
+/// eight `STATS`.
+fn parse_damage_types(text: &str) -> Result<[f32; 8], String> {
+    let mut amounts = [0.0; 8];
+    for part in text
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|x| !x.is_empty())
+    {
+        let Some((stat, amount)) = part.split_once('=') else {
+            return Err(format!("expected type=amount, got {part}"));
+        };
+        let Some(index) = STATS[..8].iter().position(|x| *x == stat.trim()) else {
+            return Err(format!("{stat} is not a damage type"));
+        };
+        let Ok(amount) = amount.trim().parse::<f32>() else {
+            return Err(format!("{amount} is not a number"));
+        };
+        if amount < 0.0 {
+            return Err(format!("{amount} is negative"));
+        }
+        amounts[index] += amount;
+    }
+    Ok(amounts)
+}
 
-            match i {
-                0 => {
-                    piece.name = String::with_capacity(MAX_NAME_LENGTH);
-                    piece.name.push_str(&td.text().trim().to_lowercase());
-                }
-                1 => {
-                    piece.physical = num;
-                }
-                2 => {
-                    piece.strike = num;
-                }
-                3 => {
-                    piece.slash = num;
-                }
-                4 => {
-                    piece.pierce = num;
-                }
-                5 => {
-                    piece.magic = num;
-                }
-                6 => {
-                    piece.fire = num;
-                }
-                7 => {
-                    piece.lightning = num;
-                }
-                8 => {
-                    piece.holy = num;
-                }
-                9 => {
-                    piece.immunity = num;
-                }
-                10 => {
-                    piece.robustness = num;
-                }
-                11 => {
-                    piece.focus = num;
-                }
-                12 => {
-                    piece.vitality = num;
-                }
-                13 => {
-                    piece.poise = num;
-                }
-                14 => {
-                    piece.weight = num;
-                }
-                _ => {}
-            }
+/// A mix of incoming damage, e.g. 60% slash, 30% holy and 10% fire.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DamageProfile {
+    pub name: String,
+    /// Share of each damage type, in the order of the first eight `STATS`.
+    pub weights: [f32; 8],
+}
+
+impl DamageProfile {
+    /// The damage that's left of this mix once `buffs` take their share, so armor can be chosen
+    /// for the damage the buffs let through.
+    #[must_use]
+    pub fn with_buffs(&self, buffs: &[usize]) -> Self {
+        let mut result = self.clone();
+        for (weight, damage) in result.weights.iter_mut().zip(buff_damage(buffs)) {
+            *weight *= damage;
         }
-        match maximize_stat {
-            0 => {
-                piece.maximize_stat = piece.physical;
-            }
-            1 => {
-                piece.maximize_stat = piece.strike;
-            }
-            2 => {
-                piece.maximize_stat = piece.slash;
-            }
-            3 => {
-                piece.maximize_stat = piece.pierce;
-            }
-            4 => {
-                piece.maximize_stat = piece.magic;
-            }
-            5 => {
-                piece.maximize_stat = piece.fire;
-            }
-            6 => {
-                piece.maximize_stat = piece.lightning;
-            }
-            7 => {
-                piece.maximize_stat = piece.holy;
-            }
-            8 => {
-                piece.maximize_stat = piece.immunity;
-            }
-            9 => {
-                piece.maximize_stat = piece.robustness;
-            }
-            10 => {
-                piece.maximize_stat = piece.focus;
-            }
-            11 => {
-                piece.maximize_stat = piece.vitality;
-            }
-            12 => {
-                piece.maximize_stat = piece.poise;
+        result
+    }
+
+    /// Parse a mix like `slash=60 holy=30 fire=10`. Shares don't need to add up to 100.
+    pub fn parse(name: &str, mix: &str) -> Result<Self, String> {
+        let weights = parse_damage_types(mix)?;
+        if weights.iter().sum::<f32>() <= 0.0 {
+            return Err(format!("{name} has no damage"));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            weights,
+        })
+    }
+
+    /// Fraction of the incoming damage that gets through the set's negation.
+    #[must_use]
+    pub fn damage_taken(&self, set: &ArmorSet) -> f32 {
+        let total: f32 = self.weights.iter().sum();
+        self.weights
+            .iter()
+            .enumerate()
+            .map(|(index, weight)| weight / total * (1.0 - f32::from(set.stat(index)) / 1000.0))
+            .sum()
+    }
+}
+
+impl std::fmt::Display for DamageProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let total: f32 = self.weights.iter().sum();
+        write!(f, "{}:", self.name)?;
+        for (index, weight) in self.weights.iter().enumerate() {
+            if *weight > 0.0 {
+                write!(f, " {}={:.0}", STATS[index], weight / total * 100.0)?;
             }
-            _ => {}
         }
-        pieces.push(piece);
+        Ok(())
     }
-    pieces
 }
 
+/// One profile per damage type, plus an even split across all of them.
 #[must_use]
-pub fn load_from_file(slot: &Slot, xdg_dirs: &xdg::BaseDirectories) -> Option<String> {
-    let file = xdg_dirs.get_cache_file(format!("{slot}.html"));
-    if Path::exists(&file) {
+pub fn preset_profiles() -> Vec<DamageProfile> {
+    let mut profiles: Vec<DamageProfile> = STATS[..8]
+        .iter()
+        .enumerate()
+        .map(|(index, stat)| {
+            let mut weights = [0.0; 8];
+            weights[index] = 1.0;
+            DamageProfile {
+                name: (*stat).to_string(),
+                weights,
+            }
+        })
+        .collect();
+    profiles.push(DamageProfile {
+        name: "even".to_string(),
+        weights: [1.0; 8],
+    });
+    profiles
+}
+
+/// Parse profiles from lines like `malenia: slash=60 pierce=40`. Blank lines and lines starting
+/// with `#` are skipped.
+pub fn parse_profiles(text: &str) -> Result<Vec<DamageProfile>, String> {
+    let mut profiles: Vec<DamageProfile> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((name, mix)) = line.split_once(':') else {
+            return Err(format!("line {}: expected name: mix", number + 1));
+        };
+        let profile = DamageProfile::parse(name.trim(), mix)
+            .map_err(|e| format!("line {}: {e}", number + 1))?;
+        profiles.push(profile);
+    }
+    Ok(profiles)
+}
+
+/// Load the user's damage profiles from `damage_profiles.txt` in the config directory. A missing
+/// file means no profiles.
+pub fn load_profiles() -> Result<Vec<DamageProfile>, String> {
+    let Ok(xdg_dirs) = xdg::BaseDirectories::with_prefix("elden_boc") else {
+        return Err("could not get xdg directories".to_string());
+    };
+    let Some(path) = xdg_dirs.find_config_file("damage_profiles.txt") else {
+        return Ok(Vec::new());
+    };
+    let text = std::fs::read_to_string(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+    parse_profiles(&text).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// A lower bound on one stat of the set, e.g. robustness of at least 40.0.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Constraint {
+    /// Index of the stat in `STATS`.
+    pub stat: usize,
+    pub min: u16,
+}
+
+impl Constraint {
+    /// Parse a bound like `robustness=40.5`, in the units the set is displayed in.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let Some((stat, min)) = text.split_once('=') else {
+            return Err(format!("expected stat=minimum, got {text}"));
+        };
+        let Some(stat) = STATS.iter().position(|x| *x == stat.trim()) else {
+            return Err(format!("{stat} is not a stat"));
+        };
+        let Ok(min) = min.trim().parse::<f32>() else {
+            return Err(format!("{min} is not a number"));
+        };
+        Ok(Self {
+            stat,
+            min: (min * 10.0).round() as u16,
+        })
+    }
+
+    /// What's left of the bound once `talismans`, indexes into `TALISMANS`, add their flat
+    /// resistances, e.g. robustness of 310 with a Stalwart Horn worn for a bound of 400.
+    #[must_use]
+    pub fn without_talismans(&self, talismans: &[usize]) -> Self {
+        let bonus = |stat: usize| {
+            talismans
+                .iter()
+                .map(|x| TALISMANS[*x].stats[stat])
+                .sum::<u16>()
+        };
+        let bonus = match self.stat {
+            8..=11 => bonus(self.stat),
+            16 => RESISTANCE_STATS.iter().map(|&x| bonus(x)).sum(),
+            _ => 0,
+        };
+        Self {
+            stat: self.stat,
+            min: self.min.saturating_sub(bonus),
+        }
+    }
+}
+
+/// Highest rune level, with every attribute at 99.
+pub const MAX_LEVEL: u16 = 713;
+
+/// A character's rune level and the attributes that resistances and defense grow with.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Character {
+    pub level: u16,
+    pub vigor: u8,
+    pub mind: u8,
+    pub endurance: u8,
+    pub strength: u8,
+    pub intelligence: u8,
+    pub arcane: u8,
+}
+
+/// Straight lines between `points`, flat past either end.
+fn curve(points: &[(f64, f64)], x: f64) -> f64 {
+    let Some(first) = points.first() else {
+        return 0.0;
+    };
+    if x <= first.0 {
+        return first.1;
+    }
+    for pair in points.windows(2) {
+        let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+        if x <= x1 {
+            return y0 + (y1 - y0) * (x - x0) / (x1 - x0);
+        }
+    }
+    points[points.len() - 1].1
+}
+
+/// Status resistance from rune level.
+const RESISTANCE_LEVEL_CURVE: [(f64, f64); 5] = [
+    (1.0, 75.0),
+    (150.0, 105.0),
+    (190.0, 135.0),
+    (240.0, 145.0),
+    (792.0, 180.0),
+];
+
+/// Status resistance from the attribute a resistance grows with.
+const RESISTANCE_ATTRIBUTE_CURVE: [(f64, f64); 5] = [
+    (1.0, 0.0),
+    (30.0, 30.0),
+    (40.0, 40.0),
+    (60.0, 60.0),
+    (99.0, 90.0),
+];
+
+/// Flat defense from rune level.
+const DEFENSE_LEVEL_CURVE: [(f64, f64); 5] = [
+    (1.0, 40.0),
+    (71.0, 100.0),
+    (91.0, 120.0),
+    (161.0, 135.0),
+    (713.0, 160.0),
+];
+
+/// Flat defense from the attribute a defense grows with.
+const DEFENSE_ATTRIBUTE_CURVE: [(f64, f64); 5] = [
+    (1.0, 0.0),
+    (30.0, 10.0),
+    (40.0, 15.0),
+    (60.0, 30.0),
+    (99.0, 40.0),
+];
+
+impl Character {
+    /// Flat defense against each damage type, in the order of the first eight `STATS`. The
+    /// physical types grow with Strength, then magic with Intelligence, fire with Vigor,
+    /// lightning with Endurance and holy with Arcane, all on top of rune level.
+    #[must_use]
+    pub fn defense(&self) -> [f32; 8] {
+        let level = curve(&DEFENSE_LEVEL_CURVE, f64::from(self.level));
+        [
+            self.strength,
+            self.strength,
+            self.strength,
+            self.strength,
+            self.intelligence,
+            self.vigor,
+            self.endurance,
+            self.arcane,
+        ]
+        .map(|attribute| {
+            let total = level + curve(&DEFENSE_ATTRIBUTE_CURVE, f64::from(attribute));
+            total.floor() as f32
+        })
+    }
+
+    /// Immunity, robustness, focus and vitality before armor, in tenths like piece stats. They
+    /// grow with rune level plus Vigor, Endurance, Mind and Arcane respectively.
+    #[must_use]
+    pub fn resistances(&self) -> [u16; 4] {
+        let level = curve(&RESISTANCE_LEVEL_CURVE, f64::from(self.level));
+        [self.vigor, self.endurance, self.mind, self.arcane].map(|attribute| {
+            let total = level + curve(&RESISTANCE_ATTRIBUTE_CURVE, f64::from(attribute));
+            // The game shows whole numbers and drops the rest.
+            (total.floor() * 10.0) as u16
+        })
+    }
+
+    /// What the character adds to the stat at `index` in `STATS` before armor.
+    #[must_use]
+    pub fn stat(&self, index: usize) -> u16 {
+        let resistances = self.resistances();
+        match index {
+            8..=11 => resistances[index - 8],
+            16 => resistances.iter().sum(),
+            _ => 0,
+        }
+    }
+
+    /// The armor's share of a bound on the character's in-game total, e.g. the armor robustness
+    /// needed for a total robustness of 400.
+    #[must_use]
+    pub fn armor_constraint(&self, total: &Constraint) -> Constraint {
+        Constraint {
+            stat: total.stat,
+            min: total.min.saturating_sub(self.stat(total.stat)),
+        }
+    }
+}
+
+/// Share of an attack's power that gets past flat defense. Strong attacks against weak defense
+/// keep up to 90%, weak attacks against strong defense as little as 10%.
+#[must_use]
+pub fn defense_multiplier(attack_power: f32, defense: f32) -> f32 {
+    if defense <= 0.0 {
+        return 0.9;
+    }
+    let ratio = attack_power / defense;
+    if ratio > 8.0 {
+        0.9
+    } else if ratio > 2.5 {
+        0.9 - 0.8 / 121.0 * (ratio - 8.0).powi(2)
+    } else if ratio > 1.0 {
+        0.7 - 0.4 / 3.0 * (ratio - 2.5).powi(2)
+    } else if ratio > 0.125 {
+        0.1 + 0.3 * ((ratio - 0.125) / 0.875).powi(2)
+    } else {
+        0.1
+    }
+}
+
+/// An enemy attack's power per damage type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attack {
+    /// Attack power of each damage type, in the order of the first eight `STATS`.
+    pub power: [f32; 8],
+}
+
+impl Attack {
+    /// Parse attack power like `slash=300 fire=150`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let power = parse_damage_types(text)?;
+        if power.iter().sum::<f32>() <= 0.0 {
+            return Err(format!("{text} has no attack power"));
+        }
+        Ok(Self { power })
+    }
+
+    /// HP lost to one hit, after the character's defense and then the set's negation.
+    #[must_use]
+    pub fn damage(&self, set: &ArmorSet, character: &Character) -> f32 {
+        let negation: [u16; 8] = std::array::from_fn(|index| set.stat(index));
+        damage_through(&self.power, &negation, &character.defense())
+    }
+
+    /// Number of hits that take `hp` to zero.
+    #[must_use]
+    pub fn hits_to_die(&self, set: &ArmorSet, character: &Character, hp: u16) -> u32 {
+        hits_to_die(f64::from(self.damage(set, character)), hp)
+    }
+}
+
+/// HP lost to a hit of `power` per damage type through `defense` and then `negation`, in the
+/// tenths of a percent that sets use.
+fn damage_through(power: &[f32; 8], negation: &[u16; 8], defense: &[f32; 8]) -> f32 {
+    (0..8)
+        .map(|index| {
+            let power = power[index];
+            power
+                * defense_multiplier(power, defense[index])
+                * (1.0 - f32::from(negation[index]) / 1000.0)
+        })
+        .sum()
+}
+
+/// Number of hits of `damage` that take `hp` to zero.
+fn hits_to_die(damage: f64, hp: u16) -> u32 {
+    if damage <= 0.0 {
+        return u32::MAX;
+    }
+    (f64::from(hp) / damage).ceil() as u32
+}
+
+/// Hits of `poise_damage` that `poise` takes before the character staggers, both in tenths. The
+/// stagger comes with the hit that brings the damage taken up to the poise.
+#[must_use]
+pub fn hits_before_stagger(poise: u16, poise_damage: u16) -> u32 {
+    if poise_damage == 0 {
+        return u32::MAX;
+    }
+    u32::from(poise)
+        .div_ceil(u32::from(poise_damage))
+        .saturating_sub(1)
+}
+
+/// Armor poise needed to take `hits` of `poise_damage` without staggering while wearing
+/// `talismans`, indexes into `TALISMANS`, whose poise bonuses apply on top of the armor. A need
+/// past what a set can hold comes out as `u16::MAX`, which no armor comes close to.
+#[must_use]
+pub fn stagger_constraint(hits: u32, poise_damage: u16, talismans: &[usize]) -> Constraint {
+    let needed = u64::from(hits) * u64::from(poise_damage) + 1;
+    let (flat, multiplier) =
+        talismans
+            .iter()
+            .map(|x| &TALISMANS[*x])
+            .fold((0, 1000), |(flat, multiplier), talisman| {
+                (
+                    flat + u64::from(talisman.stats[12]),
+                    multiplier + u64::from(talisman.poise),
+                )
+            });
+    let min = (needed * 1000).div_ceil(multiplier).saturating_sub(flat);
+    Constraint {
+        stat: 12,
+        min: min.min(u64::from(u16::MAX)) as u16,
+    }
+}
+
+/// Other names players use for the statuses in `STATUSES`.
+const STATUS_ALIASES: [(&str, &str); 5] = [
+    ("rot", "scarlet rot"),
+    ("bleed", "hemorrhage"),
+    ("blood loss", "hemorrhage"),
+    ("frost", "frostbite"),
+    ("blight", "death blight"),
+];
+
+/// An enemy attack's buildup of one status per hit, e.g. 55 hemorrhage.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Buildup {
+    /// Index of the status in `STATUSES`.
+    pub status: usize,
+    /// Buildup per hit in tenths, like resistances.
+    pub per_hit: u16,
+}
+
+impl Buildup {
+    /// Parse buildup like `hemorrhage=55` or `bleed=55`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let Some((status, per_hit)) = text.split_once('=') else {
+            return Err(format!("expected status=buildup, got {text}"));
+        };
+        let status = status.trim().to_lowercase();
+        let name = STATUS_ALIASES
+            .iter()
+            .find(|x| x.0 == status)
+            .map_or(status.as_str(), |x| x.1);
+        let Some(status) = STATUSES.iter().position(|x| x.0 == name) else {
+            return Err(format!("{status} is not a status"));
+        };
+        match per_hit.trim().parse::<f32>() {
+            Ok(num) if num > 0.0 => Ok(Self {
+                status,
+                per_hit: (num * 10.0).round() as u16,
+            }),
+            _ => Err(format!("{per_hit} is not a positive number")),
+        }
+    }
+
+    /// Hits the set takes before the status procs, on the hit that brings the buildup up to the
+    /// resistance.
+    #[must_use]
+    pub fn hits_before_proc(&self, set: &ArmorSet) -> u32 {
+        u32::from(set.stat(STATUSES[self.status].1))
+            .div_ceil(u32::from(self.per_hit))
+            .saturating_sub(1)
+    }
+
+    /// Bound on the total resistance needed to take `hits` before the status procs. A need past
+    /// what a set can hold comes out as `u16::MAX`, like in `stagger_constraint()`.
+    #[must_use]
+    pub fn constraint(&self, hits: u32) -> Constraint {
+        let min = u64::from(hits) * u64::from(self.per_hit) + 1;
+        Constraint {
+            stat: STATUSES[self.status].1,
+            min: min.min(u64::from(u16::MAX)) as u16,
+        }
+    }
+}
+
+/// Find the set by the names of its pieces in `pieces`, an empty name for an empty slot.
+pub fn find_set(pieces: &[ArmorPiece], names: [&str; 4]) -> Result<ArmorSet, String> {
+    let slots = [Slot::Helm, Slot::Chest, Slot::Gauntlets, Slot::Legs];
+    let mut found: Vec<ArmorPiece> = Vec::new();
+    for (slot, name) in slots.into_iter().zip(names) {
+        let name = name.trim().to_lowercase();
+        let piece = if name.is_empty() {
+            ArmorPiece::new(slot)
+        } else {
+            let Some(piece) = pieces.iter().find(|x| x.slot == slot && x.name == name) else {
+                return Err(format!("no {slot} named {name}"));
+            };
+            piece.clone()
+        };
+        found.push(piece);
+    }
+    let [helm, chest, gauntlets, legs]: [ArmorPiece; 4] =
+        found.try_into().expect("one piece per slot");
+    Ok(ArmorSet::from(helm, chest, gauntlets, legs))
+}
+
+/// What a boss hits with: a damage mix plus the status effects it builds up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Boss {
+    pub profile: DamageProfile,
+    /// Indexes into `STATUSES`.
+    pub statuses: Vec<usize>,
+}
+
+impl Boss {
+    /// Constraints keeping the resistance against each of the boss's statuses at `min` or more.
+    #[must_use]
+    pub fn constraints(&self, min: u16) -> Vec<Constraint> {
+        let mut constraints: Vec<Constraint> = Vec::new();
+        for status in &self.statuses {
+            let stat = STATUSES[*status].1;
+            if constraints.iter().all(|x| x.stat != stat) {
+                constraints.push(Constraint { stat, min });
+            }
+        }
+        constraints
+    }
+}
+
+impl std::fmt::Display for Boss {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.profile)?;
+        for (index, status) in self.statuses.iter().enumerate() {
+            let separator = if index == 0 { " |" } else { "," };
+            write!(f, "{separator} {}", STATUSES[*status].0)?;
+        }
+        Ok(())
+    }
+}
+
+/// The bosses built into the crate.
+#[must_use]
+pub fn bosses() -> Vec<Boss> {
+    parse_bosses(BOSSES).expect("built in boss data is malformed")
+}
+
+/// Parse lines like `malenia | slash=60 pierce=40 | scarlet rot`. Blank lines and lines starting
+/// with `#` are skipped.
+pub fn parse_bosses(text: &str) -> Result<Vec<Boss>, String> {
+    let mut bosses: Vec<Boss> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('|').map(str::trim).collect();
+        let [name, mix, statuses] = fields[..] else {
+            return Err(format!(
+                "line {}: expected name | mix | statuses",
+                number + 1
+            ));
+        };
+        let profile =
+            DamageProfile::parse(name, mix).map_err(|e| format!("line {}: {e}", number + 1))?;
+        let mut indexes: Vec<usize> = Vec::new();
+        for status in statuses.split(',').map(str::trim).filter(|x| !x.is_empty()) {
+            let Some(index) = STATUSES.iter().position(|x| x.0 == status) else {
+                return Err(format!("line {}: {status} is not a status", number + 1));
+            };
+            indexes.push(index);
+        }
+        bosses.push(Boss {
+            profile,
+            statuses: indexes,
+        });
+    }
+    Ok(bosses)
+}
+
+/// Number of single character edits to turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, x) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, y) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(x != *y);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Find the boss whose name best matches `query`, allowing for partial names and typos. A name
+/// equal to `query` always wins. Otherwise, if several bosses match equally well, like both
+/// Radahns for "radahn", returns them all as the error so the user can pick one; the error is
+/// empty if no boss matches.
+///
+/// # Errors
+///
+/// Returns the equally good candidates if there isn't exactly one best match.
+pub fn find_boss<'a>(bosses: &'a [Boss], query: &str) -> Result<&'a Boss, Vec<&'a Boss>> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Err(Vec::new());
+    }
+    if let Some(boss) = bosses.iter().find(|x| x.profile.name == query) {
+        return Ok(boss);
+    }
+    let length = query.chars().count();
+    let distance = |boss: &Boss| {
+        let name = boss.profile.name.as_str();
+        // A letter or two is part of too many names to count as a partial name.
+        if length >= 3 && name.contains(&query) {
+            return 0;
+        }
+        // Compare against each word too, so "malenia" is close to "malenia, blade of miquella".
+        name.split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|x| !x.is_empty())
+            .map(|word| edit_distance(&query, word))
+            .chain(std::iter::once(edit_distance(&query, name)))
+            .min()
+            .unwrap_or(usize::MAX)
+    };
+    let matches: Vec<(usize, &Boss)> = bosses
+        .iter()
+        .map(|boss| (distance(boss), boss))
+        .filter(|(distance, _)| *distance <= length / 3)
+        .collect();
+    let Some(best) = matches.iter().map(|(distance, _)| *distance).min() else {
+        return Err(Vec::new());
+    };
+    let candidates: Vec<&Boss> = matches
+        .into_iter()
+        .filter(|(distance, _)| *distance == best)
+        .map(|(_, boss)| boss)
+        .collect();
+    match candidates[..] {
+        [boss] => Ok(boss),
+        _ => Err(candidates),
+    }
+}
+
+/// Dodge roll speed, set by how much of the max equip load is in use.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Roll {
+    Light,
+    Medium,
+    Heavy,
+}
+
+impl Roll {
+    /// Percentage of the max equip load the roll is kept below. The heavy roll is kept up to and
+    /// including the max, see `roll_budget()`.
+    #[must_use]
+    pub const fn threshold(self) -> u32 {
+        match self {
+            Self::Light => 30,
+            Self::Medium => 70,
+            Self::Heavy => 100,
+        }
+    }
+}
+
+impl std::fmt::Display for Roll {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// A talisman that changes what armor fits, or adds to the stats armor is chosen for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Talisman {
+    pub name: &'static str,
+    /// Variants of the same talisman share a group, and only one of them can be worn.
+    pub group: &'static str,
+    /// Weight in tenths, like piece weights.
+    pub weight: u16,
+    /// Max equip load bonus in tenths of a percent.
+    pub equip_load: u16,
+    /// Bonus to each stat in `STATS` order, in tenths like piece stats. The first eight are
+    /// negation, which multiplies with the armor's like buffs do; the rest are flat.
+    pub stats: [u16; 13],
+    /// Poise bonus in tenths of a percent, applied after the flat bonuses.
+    pub poise: u16,
+}
+
+/// Talismans that raise max equip load, negation, resistances or poise.
+pub const TALISMANS: [Talisman; 29] = [
+    Talisman {
+        name: "arsenal charm",
+        group: "arsenal charm",
+        weight: 9,
+        equip_load: 150,
+        stats: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "arsenal charm +1",
+        group: "arsenal charm",
+        weight: 10,
+        equip_load: 170,
+        stats: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "great-jar's arsenal",
+        group: "great-jar's arsenal",
+        weight: 15,
+        equip_load: 190,
+        stats: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "erdtree's favor",
+        group: "erdtree's favor",
+        weight: 15,
+        equip_load: 50,
+        stats: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "erdtree's favor +1",
+        group: "erdtree's favor",
+        weight: 15,
+        equip_load: 65,
+        stats: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "erdtree's favor +2",
+        group: "erdtree's favor",
+        weight: 15,
+        equip_load: 80,
+        stats: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "dragoncrest shield talisman",
+        group: "dragoncrest shield",
+        weight: 6,
+        equip_load: 0,
+        stats: [100, 100, 100, 100, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "dragoncrest shield talisman +1",
+        group: "dragoncrest shield",
+        weight: 8,
+        equip_load: 0,
+        stats: [130, 130, 130, 130, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "dragoncrest shield talisman +2",
+        group: "dragoncrest shield",
+        weight: 10,
+        equip_load: 0,
+        stats: [170, 170, 170, 170, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "dragoncrest greatshield talisman",
+        group: "dragoncrest greatshield",
+        weight: 12,
+        equip_load: 0,
+        stats: [200, 200, 200, 200, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "spelldrake talisman",
+        group: "spelldrake",
+        weight: 6,
+        equip_load: 0,
+        stats: [0, 0, 0, 0, 130, 0, 0, 0, 0, 0, 0, 0, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "spelldrake talisman +1",
+        group: "spelldrake",
+        weight: 8,
+        equip_load: 0,
+        stats: [0, 0, 0, 0, 170, 0, 0, 0, 0, 0, 0, 0, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "spelldrake talisman +2",
+        group: "spelldrake",
+        weight: 10,
+        equip_load: 0,
+        stats: [0, 0, 0, 0, 200, 0, 0, 0, 0, 0, 0, 0, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "flamedrake talisman",
+        group: "flamedrake",
+        weight: 6,
+        equip_load: 0,
+        stats: [0, 0, 0, 0, 0, 130, 0, 0, 0, 0, 0, 0, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "flamedrake talisman +1",
+        group: "flamedrake",
+        weight: 8,
+        equip_load: 0,
+        stats: [0, 0, 0, 0, 0, 170, 0, 0, 0, 0, 0, 0, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "flamedrake talisman +2",
+        group: "flamedrake",
+        weight: 10,
+        equip_load: 0,
+        stats: [0, 0, 0, 0, 0, 200, 0, 0, 0, 0, 0, 0, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "boltdrake talisman",
+        group: "boltdrake",
+        weight: 6,
+        equip_load: 0,
+        stats: [0, 0, 0, 0, 0, 0, 130, 0, 0, 0, 0, 0, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "boltdrake talisman +1",
+        group: "boltdrake",
+        weight: 8,
+        equip_load: 0,
+        stats: [0, 0, 0, 0, 0, 0, 170, 0, 0, 0, 0, 0, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "boltdrake talisman +2",
+        group: "boltdrake",
+        weight: 10,
+        equip_load: 0,
+        stats: [0, 0, 0, 0, 0, 0, 200, 0, 0, 0, 0, 0, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "haligdrake talisman",
+        group: "haligdrake",
+        weight: 6,
+        equip_load: 0,
+        stats: [0, 0, 0, 0, 0, 0, 0, 130, 0, 0, 0, 0, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "haligdrake talisman +1",
+        group: "haligdrake",
+        weight: 8,
+        equip_load: 0,
+        stats: [0, 0, 0, 0, 0, 0, 0, 170, 0, 0, 0, 0, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "haligdrake talisman +2",
+        group: "haligdrake",
+        weight: 10,
+        equip_load: 0,
+        stats: [0, 0, 0, 0, 0, 0, 0, 200, 0, 0, 0, 0, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "bull-goat's talisman",
+        group: "bull-goat's",
+        weight: 15,
+        equip_load: 0,
+        stats: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        poise: 330,
+    },
+    Talisman {
+        name: "immunizing horn",
+        group: "immunizing horn",
+        weight: 6,
+        equip_load: 0,
+        stats: [0, 0, 0, 0, 0, 0, 0, 0, 900, 0, 0, 0, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "stalwart horn",
+        group: "stalwart horn",
+        weight: 6,
+        equip_load: 0,
+        stats: [0, 0, 0, 0, 0, 0, 0, 0, 0, 900, 0, 0, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "clarifying horn",
+        group: "clarifying horn",
+        weight: 6,
+        equip_load: 0,
+        stats: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 900, 0, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "prince of death's pustule",
+        group: "prince of death's pustule",
+        weight: 6,
+        equip_load: 0,
+        stats: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 900, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "mottled necklace",
+        group: "mottled necklace",
+        weight: 8,
+        equip_load: 0,
+        stats: [0, 0, 0, 0, 0, 0, 0, 0, 400, 400, 400, 0, 0],
+        poise: 0,
+    },
+    Talisman {
+        name: "mottled necklace +1",
+        group: "mottled necklace",
+        weight: 9,
+        equip_load: 0,
+        stats: [0, 0, 0, 0, 0, 0, 0, 0, 500, 500, 500, 0, 0],
+        poise: 0,
+    },
+];
+
+/// Max equip load at an Endurance level before talismans. Follows the game's curve: 45 up to
+/// Endurance 8, then 72 at 25, 120 at 60 and 160 at 99.
+fn equip_load_curve(endurance: u8) -> f64 {
+    let endurance = f64::from(endurance.clamp(1, 99));
+    if endurance <= 8.0 {
+        45.0
+    } else if endurance <= 25.0 {
+        45.0 + 27.0 * ((endurance - 8.0) / 17.0).powf(1.1)
+    } else if endurance <= 60.0 {
+        72.0 + 48.0 * (1.0 - (1.0 - (endurance - 25.0) / 35.0).powf(1.2))
+    } else {
+        120.0 + 40.0 * ((endurance - 60.0) / 39.0)
+    }
+}
+
+/// Max equip load at an Endurance level, in tenths like piece weights.
+#[must_use]
+pub fn max_equip_load(endurance: u8) -> u16 {
+    max_equip_load_with(endurance, &[])
+}
+
+/// Max equip load at an Endurance level while wearing `talismans`, indexes into `TALISMANS`.
+/// Each talisman multiplies the load.
+#[must_use]
+pub fn max_equip_load_with(endurance: u8, talismans: &[usize]) -> u16 {
+    let load = talismans
+        .iter()
+        .fold(equip_load_curve(endurance), |load, x| {
+            load * (1.0 + f64::from(TALISMANS[*x].equip_load) / 1000.0)
+        });
+    // The game shows one decimal and drops the rest.
+    (load * 10.0 + 1e-9).floor() as u16
+}
+
+/// Heaviest total equip load that still gets `roll`, in tenths. Loads up to 29.9% of the max get
+/// a light roll, and 69.9% a medium one, but a load of exactly the max still rolls heavy. The
+/// result can be compared inclusively, `weight <= budget`.
+#[must_use]
+pub fn roll_budget(max_load: u16, roll: Roll) -> u16 {
+    let limit = u32::from(max_load) * roll.threshold();
+    match roll {
+        // Largest weight with weight * 100 <= limit.
+        Roll::Heavy => (limit / 100) as u16,
+        // Largest weight with weight * 100 < limit.
+        Roll::Light | Roll::Medium => limit.div_ceil(100).saturating_sub(1) as u16,
+    }
+}
+
+/// Weight left for armor at an Endurance level, after the weapons and other gear in `equipped`
+/// and the `talismans` from `TALISMANS`.
+#[must_use]
+pub fn armor_budget(endurance: u8, roll: Roll, equipped: u16, talismans: &[usize]) -> u16 {
+    let talisman_weight: u16 = talismans.iter().map(|x| TALISMANS[*x].weight).sum();
+    roll_budget(max_equip_load_with(endurance, talismans), roll)
+        .saturating_sub(equipped)
+        .saturating_sub(talisman_weight)
+}
+
+/// Lowest Endurance level whose `roll` armor budget carries `weight`, after the gear in `equipped`
+/// and the `talismans` from `TALISMANS`, or `None` if even 99 doesn't.
+#[must_use]
+pub fn min_endurance(weight: u16, roll: Roll, equipped: u16, talismans: &[usize]) -> Option<u8> {
+    (1..=99).find(|&endurance| armor_budget(endurance, roll, equipped, talismans) >= weight)
+}
+
+/// Lowest Endurance level whose `roll` armor budget fits a set from `pieces` that meets
+/// `constraints`, with the best such set at that level.
+#[must_use]
+pub fn min_endurance_constrained(
+    pieces: &[ArmorPiece],
+    constraints: &[Constraint],
+    roll: Roll,
+    equipped: u16,
+    talismans: &[usize],
+) -> Option<(u8, ArmorSet)> {
+    println!("Finding the best set...");
+    let best = |endurance: u8| {
+        let weight_restriction = armor_budget(endurance, roll, equipped, talismans);
+        let pieces: Vec<ArmorPiece> = pieces
+            .iter()
+            .filter(|x| x.weight <= weight_restriction)
+            .cloned()
+            .collect();
+        best_constrained(weight_restriction, pieces, constraints)
+    };
+    // Budgets only grow with Endurance, so the levels that fit a set are all above the first one.
+    let levels: Vec<u8> = (1..=99).collect();
+    let first = levels.partition_point(|&endurance| best(endurance).is_none());
+    let endurance = *levels.get(first)?;
+    best(endurance).map(|set| (endurance, set))
+}
+
+/// Runes to level up from rune level `level` to the next.
+#[must_use]
+pub fn level_cost(level: u16) -> u64 {
+    let level = f64::from(level) + 81.0;
+    let x = ((level - 92.0) * 0.02).max(0.0);
+    ((x + 0.1) * level * level + 1.0).floor() as u64
+}
+
+/// Runes to level up `levels` times from rune level `level`. Levels past `MAX_LEVEL` can't be
+/// bought, so they aren't counted.
+#[must_use]
+pub fn levels_cost(level: u16, levels: u16) -> u64 {
+    (level..level.saturating_add(levels).min(MAX_LEVEL))
+        .map(level_cost)
+        .sum()
+}
+
+/// Every change of one equip load talisman from `worn`: each worn one taken off, and each other
+/// one put on, in place of a worn variant from its group if there is one. Pairs a description
+/// like `+great-jar's arsenal` with the talismans worn after the change.
+#[must_use]
+pub fn talisman_toggles(worn: &[usize]) -> Vec<(String, Vec<usize>)> {
+    let mut toggles: Vec<(String, Vec<usize>)> = Vec::new();
+    for (index, talisman) in TALISMANS.iter().enumerate() {
+        if talisman.equip_load == 0 {
+            continue;
+        }
+        if worn.contains(&index) {
+            let rest = worn.iter().copied().filter(|x| *x != index).collect();
+            toggles.push((format!("-{}", talisman.name), rest));
+            continue;
+        }
+        let mut description = format!("+{}", talisman.name);
+        let mut rest: Vec<usize> = Vec::new();
+        for x in worn {
+            if TALISMANS[*x].group == talisman.group {
+                description = format!("{} -> {}", TALISMANS[*x].name, talisman.name);
+            } else {
+                rest.push(*x);
+            }
+        }
+        rest.push(index);
+        toggles.push((description, rest));
+    }
+    toggles
+}
+
+#[must_use]
+pub fn get_set(weight_restriction: u16, pieces: Vec<ArmorPiece>) -> ArmorSet {
+    let [helms, chests, gauntlets, legs] = partition(pieces);
+
+    println!("Finding the best set...");
+
+    // Helmets are used for the outer loop. Break helmets into as many chunks as we have
+    // MAX_THREADS. Make sure to add the remainder so we don't do an extra loop with
+    // thread MAX_THREADS + 1.
+    let chunk_size = helms.len() / MAX_THREADS + (helms.len() % MAX_THREADS);
+    let final_result = Arc::new(Mutex::new(ArmorSet::new()));
+    let mut threads = vec![];
+    for chunk in helms.chunks(chunk_size) {
+        let final_result_clone = Arc::clone(&final_result);
+        let chests_clone = chests.clone();
+        let gauntlets_clone = gauntlets.clone();
+        let legs_clone = legs.clone();
+        let mut result = ArmorSet::new();
+        let chunk = chunk.to_owned();
+        let handle = thread::spawn(move || {
+            for helm in chunk {
+                for chest in &chests_clone {
+                    for gauntlet in &gauntlets_clone {
+                        for leg in &legs_clone {
+                            // Don't allocate an ArmorSet if this gear is too heavy.
+                            let potential_weight =
+                                helm.weight + chest.weight + gauntlet.weight + leg.weight;
+
+                            if potential_weight > weight_restriction {
+                                continue;
+                            }
+                            // Don't allocate an ArmorSet if result.maximize_stat is better than the
+                            // potential stat.
+                            let potential_maximize_stat = helm.maximize_stat
+                                + chest.maximize_stat
+                                + gauntlet.maximize_stat
+                                + leg.maximize_stat;
+
+                            if result.maximize_stat >= potential_maximize_stat {
+                                continue;
+                            }
+
+                            // Don't allocate an ArmorSet if the maximize_stats are the same, but the
+                            // weight is not _strictly_ better. We can avoid a few more allocations if we
+                            // don't re-assign the result when there's ties, so don't consider equal weight
+                            // a contender.
+                            if result.maximize_stat == potential_maximize_stat
+                                && result.weight < potential_weight
+                            {
+                                continue;
+                            }
+
+                            // We found one that is strictly better. Replace thread-local result
+                            // with the new set.
+                            result = ArmorSet::from(
+                                helm.clone(),
+                                chest.clone(),
+                                gauntlet.clone(),
+                                leg.clone(),
+                            );
+                        }
+                    }
+                }
+            }
+            // At the end of the loop, result stores the best possible armor set using the helmets
+            // available to that chunk. See if result is better than any thread's result. If so,
+            // replace it.
+            let mut guard = final_result_clone.lock().unwrap();
+            if result.maximize_stat > guard.maximize_stat {
+                *guard = result;
+            }
+        });
+        threads.push(handle);
+    }
+    for handle in threads {
+        handle.join().unwrap();
+    }
+    let final_result = final_result.lock().unwrap();
+    final_result.clone()
+}
+
+/// Split pieces into helms, chests, gauntlets and legs.
+fn partition(pieces: Vec<ArmorPiece>) -> [Vec<ArmorPiece>; 4] {
+    let (helms, rest): (Vec<ArmorPiece>, Vec<ArmorPiece>) =
+        pieces.into_iter().partition(|x| x.slot == Slot::Helm);
+    let (chests, rest): (Vec<ArmorPiece>, Vec<ArmorPiece>) =
+        rest.into_iter().partition(|x| x.slot == Slot::Chest);
+    let (gauntlets, legs): (Vec<ArmorPiece>, Vec<ArmorPiece>) =
+        rest.into_iter().partition(|x| x.slot == Slot::Gauntlets);
+    [helms, chests, gauntlets, legs]
+}
+
+/// A stand-in piece with the best value of every stat in `pieces` and the lowest weight.
+/// No real piece in the slot can beat it, which makes it an upper bound for pruning.
+fn ceiling(pieces: &[ArmorPiece]) -> ArmorPiece {
+    let mut result = ArmorPiece::new(Slot::Empty);
+    result.weight = pieces.iter().map(|x| x.weight).min().unwrap_or(0);
+    for piece in pieces {
+        result.physical = result.physical.max(piece.physical);
+        result.strike = result.strike.max(piece.strike);
+        result.slash = result.slash.max(piece.slash);
+        result.pierce = result.pierce.max(piece.pierce);
+        result.magic = result.magic.max(piece.magic);
+        result.fire = result.fire.max(piece.fire);
+        result.lightning = result.lightning.max(piece.lightning);
+        result.holy = result.holy.max(piece.holy);
+        result.immunity = result.immunity.max(piece.immunity);
+        result.robustness = result.robustness.max(piece.robustness);
+        result.focus = result.focus.max(piece.focus);
+        result.vitality = result.vitality.max(piece.vitality);
+        result.poise = result.poise.max(piece.poise);
+        result.maximize_stat = result.maximize_stat.max(piece.maximize_stat);
+    }
+    result
+}
+
+/// Value of the stat at `index` in `STATS` for the four pieces of a set. Composites are built
+/// from the set's totals rather than summed per piece, so averages don't lose any rounding.
+fn set_stat(set: &[&ArmorPiece; 4], index: usize) -> u16 {
+    if index < 13 {
+        set.iter().map(|x| x.stat(index)).sum()
+    } else {
+        stat_of(&std::array::from_fn(|x| set_stat(set, x)), index)
+    }
+}
+
+/// Whether a set scoring `score` at `weight` should replace `best`. Ties go to the lighter set.
+fn beats(score: f64, weight: u16, best: &Option<(f64, u16, ArmorSet)>) -> bool {
+    match best {
+        None => true,
+        Some((best_score, best_weight, _)) => {
+            score > *best_score || (score == *best_score && weight < *best_weight)
+        }
+    }
+}
+
+/// Branch and bound search for the set with the highest `score` within `weight_restriction`.
+///
+/// `score` returns `None` for sets that break a constraint. Undecided slots are passed to it as
+/// `ceiling` pieces to prune whole branches, so `score` must never get worse when a stat goes up
+/// or the weight goes down.
+fn search<F>(weight_restriction: u16, pieces: Vec<ArmorPiece>, score: F) -> Option<(ArmorSet, f64)>
+where
+    F: Fn(&[&ArmorPiece; 4]) -> Option<f64> + Send + Sync + 'static,
+{
+    let [helms, chests, gauntlets, legs] = partition(pieces);
+    if helms.is_empty() || chests.is_empty() || gauntlets.is_empty() || legs.is_empty() {
+        return None;
+    }
+    let chest_ceiling = ceiling(&chests);
+    let gauntlet_ceiling = ceiling(&gauntlets);
+    let leg_ceiling = ceiling(&legs);
+    let shared = Arc::new((
+        chests,
+        gauntlets,
+        legs,
+        chest_ceiling,
+        gauntlet_ceiling,
+        leg_ceiling,
+    ));
+    let score = Arc::new(score);
+
+    let chunk_size = (helms.len() / MAX_THREADS + (helms.len() % MAX_THREADS)).max(1);
+    let mut threads = vec![];
+    for chunk in helms.chunks(chunk_size) {
+        let shared = Arc::clone(&shared);
+        let score = Arc::clone(&score);
+        let chunk = chunk.to_owned();
+        let handle = thread::spawn(move || {
+            let (chests, gauntlets, legs, chest_ceiling, gauntlet_ceiling, leg_ceiling) = &*shared;
+            let mut best: Option<(f64, u16, ArmorSet)> = None;
+            // Prune when even the ceiling pieces can't beat the best set found so far.
+            let hopeless = |set: &[&ArmorPiece; 4], best: &Option<(f64, u16, ArmorSet)>| {
+                let weight = set.iter().map(|x| x.weight).sum::<u16>();
+                weight > weight_restriction
+                    || !score(set).is_some_and(|bound| beats(bound, weight, best))
+            };
+            for helm in &chunk {
+                if hopeless(&[helm, chest_ceiling, gauntlet_ceiling, leg_ceiling], &best) {
+                    continue;
+                }
+                for chest in chests {
+                    if hopeless(&[helm, chest, gauntlet_ceiling, leg_ceiling], &best) {
+                        continue;
+                    }
+                    for gauntlet in gauntlets {
+                        if hopeless(&[helm, chest, gauntlet, leg_ceiling], &best) {
+                            continue;
+                        }
+                        for leg in legs {
+                            let set = [helm, chest, gauntlet, leg];
+                            let weight = helm.weight + chest.weight + gauntlet.weight + leg.weight;
+                            if weight > weight_restriction {
+                                continue;
+                            }
+                            let Some(value) = score(&set) else {
+                                continue;
+                            };
+                            if beats(value, weight, &best) {
+                                let result = ArmorSet::from(
+                                    helm.clone(),
+                                    chest.clone(),
+                                    gauntlet.clone(),
+                                    leg.clone(),
+                                );
+                                best = Some((value, weight, result));
+                            }
+                        }
+                    }
+                }
+            }
+            best
+        });
+        threads.push(handle);
+    }
+    let mut best: Option<(f64, u16, ArmorSet)> = None;
+    for handle in threads {
+        if let Some((value, weight, result)) = handle.join().unwrap() {
+            if beats(value, weight, &best) {
+                best = Some((value, weight, result));
+            }
+        }
+    }
+    best.map(|(value, _, result)| (result, value))
+}
+
+/// Find the set whose lowest stat among `stats` is highest, for when any of them might be the
+/// one that gets tested. Returns the set and the index of the stat that was the binding minimum.
+#[must_use]
+pub fn get_set_maximin(
+    weight_restriction: u16,
+    pieces: Vec<ArmorPiece>,
+    stats: &[usize],
+) -> (ArmorSet, usize) {
+    println!("Finding the best set...");
+    let group = stats.to_vec();
+    let result = search(weight_restriction, pieces, move |set| {
+        group
+            .iter()
+            .map(|&stat| set_stat(set, stat))
+            .min()
+            .map(f64::from)
+    })
+    .map_or_else(ArmorSet::new, |(result, _)| result);
+    let binding = stats
+        .iter()
+        .copied()
+        .min_by_key(|&stat| result.stat(stat))
+        .unwrap_or(0);
+    (result, binding)
+}
+
+/// Find the set with the most `maximize_stat` per unit of weight, among sets with at least
+/// `min_stat`. Returns the set and its ratio, or `None` if no set reaches `min_stat`.
+///
+/// The ratio isn't a sum over pieces, so it's found with Dinkelbach's method: maximize
+/// `stat - ratio * weight` with the best ratio so far until that can't get above zero.
+#[must_use]
+pub fn get_set_ratio(
+    weight_restriction: u16,
+    pieces: Vec<ArmorPiece>,
+    min_stat: u16,
+) -> Option<(ArmorSet, f64)> {
+    println!("Finding the best set...");
+    let ratio = |result: &ArmorSet| {
+        if result.weight == 0 && result.maximize_stat == 0 {
+            0.0
+        } else if result.weight == 0 {
+            f64::INFINITY
+        } else {
+            f64::from(result.maximize_stat) / f64::from(result.weight)
+        }
+    };
+    let mut lambda = 0.0;
+    let mut best: Option<(ArmorSet, f64)> = None;
+    for _ in 0..64 {
+        let (result, value) = search(weight_restriction, pieces.clone(), move |set| {
+            let stat = set.iter().map(|x| x.maximize_stat).sum::<u16>();
+            let weight = set.iter().map(|x| x.weight).sum::<u16>();
+            (stat >= min_stat).then(|| f64::from(stat) - lambda * f64::from(weight))
+        })?;
+        if best.is_some() && value <= 1e-9 {
+            break;
+        }
+        lambda = ratio(&result);
+        best = Some((result, lambda));
+        if lambda.is_infinite() {
+            break;
+        }
+    }
+    best
+}
+
+/// Find the lightest set that keeps at least `percent` percent of the best `maximize_stat`
+/// reachable within `weight_restriction`. Returns the strict optimum and the lighter set, in that
+/// order.
+#[must_use]
+pub fn get_set_near_optimal(
+    weight_restriction: u16,
+    pieces: Vec<ArmorPiece>,
+    percent: u32,
+) -> (ArmorSet, ArmorSet) {
+    let optimum = get_set(weight_restriction, pieces.clone());
+    let target = (u32::from(optimum.maximize_stat) * percent.min(100)).div_ceil(100) as u16;
+    let near = search(weight_restriction, pieces, move |set| {
+        let stat = set.iter().map(|x| x.maximize_stat).sum::<u16>();
+        let weight = set.iter().map(|x| x.weight).sum::<u16>();
+        // Weight first, then the stat to break ties between equally light sets.
+        (stat >= target).then(|| -f64::from(weight) * 65536.0 + f64::from(stat))
+    })
+    .map_or_else(|| optimum.clone(), |(result, _)| result);
+    (optimum, near)
+}
+
+/// Find the set with the most `maximize_stat` that meets every constraint, or `None` if no set
+/// within the weight meets them.
+#[must_use]
+pub fn get_set_constrained(
+    weight_restriction: u16,
+    pieces: Vec<ArmorPiece>,
+    constraints: &[Constraint],
+) -> Option<ArmorSet> {
+    println!("Finding the best set...");
+    best_constrained(weight_restriction, pieces, constraints)
+}
+
+/// `get_set_constrained()` without the progress message, for searches that run many times.
+fn best_constrained(
+    weight_restriction: u16,
+    pieces: Vec<ArmorPiece>,
+    constraints: &[Constraint],
+) -> Option<ArmorSet> {
+    let constraints = constraints.to_vec();
+    search(weight_restriction, pieces, move |set| {
+        if constraints.iter().any(|x| set_stat(set, x.stat) < x.min) {
+            return None;
+        }
+        Some(f64::from(set.iter().map(|x| x.maximize_stat).sum::<u16>()))
+    })
+    .map(|(result, _)| result)
+}
+
+/// Find the set that takes the most hits of `attack` to bring `hp` to zero with `buffs` active,
+/// indexes into `BUFFS`, and among those the one that loses the least HP per hit.
+#[must_use]
+pub fn get_set_survival(
+    weight_restriction: u16,
+    pieces: Vec<ArmorPiece>,
+    attack: &Attack,
+    character: &Character,
+    hp: u16,
+    buffs: &[usize],
+) -> ArmorSet {
+    println!("Finding the best set...");
+    let power = attack.power;
+    let defense = character.defense();
+    let left = buff_damage(buffs);
+    search(weight_restriction, pieces, move |set| {
+        let negation: [u16; 8] =
+            std::array::from_fn(|index| negation_with(set_stat(set, index), left[index]));
+        let damage = f64::from(damage_through(&power, &negation, &defense));
+        Some(f64::from(hits_to_die(damage, hp)) * 1e6 - damage)
+    })
+    .map_or_else(ArmorSet::new, |(result, _)| result)
+}
+
+/// Find the set that lets the least of `profile`'s damage through while meeting every
+/// constraint, or `None` if no set within the weight meets them.
+#[must_use]
+pub fn get_set_damage_profile(
+    weight_restriction: u16,
+    pieces: Vec<ArmorPiece>,
+    profile: &DamageProfile,
+    constraints: &[Constraint],
+) -> Option<ArmorSet> {
+    println!("Finding the best set...");
+    // Damage taken falls linearly with each type's negation, so maximize the weighted negation.
+    let weights = profile.weights;
+    let constraints = constraints.to_vec();
+    search(weight_restriction, pieces, move |set| {
+        if constraints.iter().any(|x| set_stat(set, x.stat) < x.min) {
+            return None;
+        }
+        Some(
+            weights
+                .iter()
+                .enumerate()
+                .map(|(index, weight)| f64::from(*weight) * f64::from(set_stat(set, index)))
+                .sum(),
+        )
+    })
+    .map(|(result, _)| result)
+}
+
+/// A set's weight and score, and the index of the piece in each slot's list.
+type ParetoSet = (u16, u64, [usize; 4]);
+
+/// Every set that no other set beats on both weight and the sum of `score` over its pieces,
+/// lightest first.
+fn pareto_sets(slots: &[Vec<ArmorPiece>; 4], score: impl Fn(&ArmorPiece) -> u64) -> Vec<ParetoSet> {
+    let mut sets: Vec<ParetoSet> = vec![(0, 0, [0; 4])];
+    for (slot, pieces) in slots.iter().enumerate() {
+        let scores: Vec<u64> = pieces.iter().map(&score).collect();
+        let mut combined: Vec<ParetoSet> = Vec::new();
+        for (weight, stat, indexes) in &sets {
+            for (index, piece) in pieces.iter().enumerate() {
+                let mut indexes = *indexes;
+                indexes[slot] = index;
+                combined.push((weight + piece.weight, stat + scores[index], indexes));
+            }
+        }
+        // Lightest first, and the best stat first among equal weights. Then keep only sets that
+        // beat everything lighter.
+        combined.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+        sets.clear();
+        for set in combined {
+            if sets.last().is_none_or(|best| set.1 > best.1) {
+                sets.push(set);
+            }
+        }
+    }
+    sets
+}
+
+/// Indexes of the base stats that `stat`, an index in `STATS`, is made of, or `None` for an
+/// unknown index.
+fn stat_parts(stat: usize) -> Option<Vec<usize>> {
+    match stat {
+        0..=12 => Some(vec![stat]),
+        13 => Some(PHYSICAL_STATS.to_vec()),
+        14 => Some(ELEMENTAL_STATS.to_vec()),
+        15 => Some([PHYSICAL_STATS, ELEMENTAL_STATS].concat()),
+        16 => Some(RESISTANCE_STATS.to_vec()),
+        _ => None,
+    }
+}
+
+/// Every way to wear up to `slots` of `candidates`, at most one per talisman group.
+fn talisman_combinations(candidates: &[usize], slots: usize) -> Vec<Vec<usize>> {
+    let mut combinations: Vec<Vec<usize>> = vec![Vec::new()];
+    for &candidate in candidates {
+        let group = TALISMANS[candidate].group;
+        let mut added: Vec<Vec<usize>> = Vec::new();
+        for combination in &combinations {
+            if combination.len() < slots && combination.iter().all(|x| TALISMANS[*x].group != group)
+            {
+                let mut combination = combination.clone();
+                combination.push(candidate);
+                added.push(combination);
+            }
+        }
+        combinations.extend(added);
+    }
+    combinations
+}
+
+/// Find the armor and up to `slots` of the `available` talismans that together give the most of
+/// the stat at `maximize_stat`, which the pieces were loaded for. Talismans compete with armor
+/// for equip load, and some of them raise it, so the budget comes from Endurance, roll type and
+/// the weight of the rest of the gear in `equipped`. Returns the set with the talismans' bonuses
+/// applied, and the talismans, or `None` if no set fits. An unknown `maximize_stat` is an error.
+pub fn get_set_with_talismans(
+    endurance: u8,
+    roll: Roll,
+    equipped: u16,
+    pieces: Vec<ArmorPiece>,
+    maximize_stat: usize,
+    available: &[usize],
+    slots: usize,
+) -> Result<Option<(ArmorSet, Vec<usize>)>, String> {
+    let Some(parts) = stat_parts(maximize_stat) else {
+        return Err(format!("{maximize_stat} is not an index in STATS"));
+    };
+    println!("Finding the best set...");
+    // Talismans that neither raise equip load nor add to the stat only take weight away.
+    let candidates: Vec<usize> = available
+        .iter()
+        .copied()
+        .filter(|x| {
+            let talisman = &TALISMANS[*x];
+            talisman.equip_load > 0
+                || parts.iter().any(|&stat| talisman.stats[stat] > 0)
+                || (talisman.poise > 0 && parts.contains(&12))
+        })
+        .collect();
+
+    // A negation talisman lets through a share of what the armor lets through, so each point of
+    // armor negation is worth that share once the talisman is worn. The best armor for a
+    // combination has the most of the stat's parts weighed that way, within its budget. Find
+    // those for every budget at once, for each distinct weighing.
+    let slot_pieces = partition(pieces);
+    let mut pareto: HashMap<Vec<u64>, Vec<ParetoSet>> = HashMap::new();
+
+    let mut best: Option<(u16, u16, ArmorSet, Vec<usize>)> = None;
+    for talismans in talisman_combinations(&candidates, slots) {
+        let budget = armor_budget(endurance, roll, equipped, &talismans);
+        let damage = talisman_damage(&talismans);
+        let slopes: Vec<u64> = parts
+            .iter()
+            .map(|&stat| {
+                damage
+                    .get(stat)
+                    .map_or(10000, |x| (x * 10000.0).round() as u64)
+            })
+            .collect();
+        let sets = pareto.entry(slopes.clone()).or_insert_with(|| {
+            pareto_sets(&slot_pieces, |piece| {
+                parts
+                    .iter()
+                    .zip(&slopes)
+                    .map(|(&stat, slope)| u64::from(piece.stat(stat)) * slope)
+                    .sum()
+            })
+        });
+        let Some((weight, _, indexes)) = sets.iter().rev().find(|x| x.0 <= budget) else {
+            continue;
+        };
+        let armor = ArmorSet::from(
+            slot_pieces[0][indexes[0]].clone(),
+            slot_pieces[1][indexes[1]].clone(),
+            slot_pieces[2][indexes[2]].clone(),
+            slot_pieces[3][indexes[3]].clone(),
+        );
+        let mut result = armor.with_talismans(&talismans);
+        result.maximize_stat = result.total(maximize_stat);
+        let total_weight = weight + talismans.iter().map(|x| TALISMANS[*x].weight).sum::<u16>();
+        let better = best.as_ref().is_none_or(|(stat, weight, _, _)| {
+            result.maximize_stat > *stat
+                || (result.maximize_stat == *stat && total_weight < *weight)
+        });
+        if better {
+            best = Some((result.maximize_stat, total_weight, result, talismans));
+        }
+    }
+    Ok(best.map(|(_, _, result, talismans)| (result, talismans)))
+}
+
+#[must_use]
+pub fn get_pieces_from_text(slot: &Slot, text: &str, maximize_stat: usize) -> Vec<ArmorPiece> {
+    try_pieces_from_text(slot, text, maximize_stat).unwrap_or_default()
+}
+
+/// Like `get_pieces_from_text`, but a page without an armor table is an error.
+pub fn try_pieces_from_text(
+    slot: &Slot,
+    text: &str,
+    maximize_stat: usize,
+) -> Result<Vec<ArmorPiece>, String> {
+    let mut pieces: Vec<ArmorPiece> = Vec::new();
+    let soup = Soup::new(text);
+    // Rows of every armor table, with the heading above the table. Tables without a row of
+    // armor columns, a name, the stats and the weight, are something else.
+    let mut rows = Vec::new();
+    let mut section = String::new();
+    for node in soup.tag(true).find_all() {
+        match node.name() {
+            "h1" | "h2" | "h3" | "h4" => {
+                section = node.text().split_whitespace().collect::<Vec<_>>().join(" ");
+            }
+            "table" => {
+                let table_rows: Vec<_> = node
+                    .tag("tbody")
+                    .recursive(false)
+                    .find_all()
+                    .flat_map(|tbody| tbody.tag("tr").recursive(false).find_all())
+                    .collect();
+                if table_rows
+                    .iter()
+                    .any(|row| row.tag("td").find_all().count() >= ARMOR_COLUMNS)
+                {
+                    rows.extend(table_rows.into_iter().map(|row| (section.clone(), row)));
+                }
+            }
+            _ => {}
+        }
+    }
+    if rows.is_empty() {
+        return Err(format!("{slot} page has no armor table"));
+    }
+    for (section, row) in rows {
+        let mut piece = ArmorPiece::new(Slot::Empty);
+        piece.slot = slot.clone();
+        piece.section = section;
+        for (i, td) in row.tag("td").find_all().enumerate() {
+            if i > 14 {
+                break;
+            }
+
+            let text = td.text().trim().to_string();
+            let mut num: u16 = 0;
+            if i != 0 {
+                let mut parse_result: f32;
+                parse_result = if let Ok(value) = text.parse::<f32>() {
+                    value
+                } else {
+                    // println!("Couldn't convert {} to f32, skipping {}", text, piece.name);
+                    continue;
+                };
+
+                if parse_result < 0.0 {
+                    // Ignore scarabs or other pieces with negative values.
+                    // println!("parse_result was {parse_result}, expected non-zero positive! {}", piece.name);
+                    continue;
+                }
+                parse_result *= 10.0;
+                // Pieces only ever have one significant digit,
+                // so truncating is fine here.
+                num = parse_result as u16;
+            }
+
+            match i {
+                0 => {
+                    piece.name = String::with_capacity(MAX_NAME_LENGTH);
+                    piece.name.push_str(&td.text().trim().to_lowercase());
+                }
+                1 => {
+                    piece.physical = num;
+                }
+                2 => {
+                    piece.strike = num;
+                }
+                3 => {
+                    piece.slash = num;
+                }
+                4 => {
+                    piece.pierce = num;
+                }
+                5 => {
+                    piece.magic = num;
+                }
+                6 => {
+                    piece.fire = num;
+                }
+                7 => {
+                    piece.lightning = num;
+                }
+                8 => {
+                    piece.holy = num;
+                }
+                9 => {
+                    piece.immunity = num;
+                }
+                10 => {
+                    piece.robustness = num;
+                }
+                11 => {
+                    piece.focus = num;
+                }
+                12 => {
+                    piece.vitality = num;
+                }
+                13 => {
+                    piece.poise = num;
+                }
+                14 => {
+                    piece.weight = num;
+                }
+                _ => {}
+            }
+        }
+        piece.dlc = is_dlc(&piece.name, &piece.section);
+        piece.maximize_stat = piece.total(maximize_stat);
+        pieces.push(piece);
+    }
+    if pieces.is_empty() {
+        return Err(format!("{slot} page's armor tables have no pieces"));
+    }
+    Ok(pieces)
+}
+
+/// Whether the piece `name`, listed under the heading `section`, is from Shadow of the Erdtree:
+/// either the heading says so or the piece is in `data/dlc_pieces.txt`.
+#[must_use]
+pub fn is_dlc(name: &str, section: &str) -> bool {
+    let section = section.to_lowercase();
+    section.contains("shadow of the erdtree")
+        || section.split_whitespace().any(|x| x == "dlc")
+        || DLC_PIECES
+            .lines()
+            .map(str::trim)
+            .filter(|x| !x.starts_with('#'))
+            .any(|x| x == name)
+}
+
+/// Drop the Shadow of the Erdtree pieces from `pieces` unless `include_dlc`.
+pub fn filter_dlc(pieces: &mut Vec<ArmorPiece>, include_dlc: bool) {
+    if !include_dlc {
+        pieces.retain(|x| !x.dlc);
+    }
+}
+
+/// The `dlc` setting in `config`, e.g. `dlc = no`, or `None` if it isn't set.
+pub fn dlc_from_config(config: &HashMap<String, String>) -> Result<Option<bool>, String> {
+    switch_from_config(config, "dlc")
+}
+
+/// The yes or no setting `key` in `config`, or `None` if it isn't set.
+pub fn switch_from_config(
+    config: &HashMap<String, String>,
+    key: &str,
+) -> Result<Option<bool>, String> {
+    match config.get(key).map(|x| x.to_lowercase()).as_deref() {
+        None => Ok(None),
+        Some("yes" | "true" | "on") => Ok(Some(true)),
+        Some("no" | "false" | "off") => Ok(Some(false)),
+        Some(value) => Err(format!("{key}: expected yes or no, got {value}")),
+    }
+}
+
+/// A piece that can't be obtained in game, and why.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Unobtainable {
+    pub slot: Slot,
+    /// The piece's name as parsed from the wiki, in lowercase.
+    pub name: String,
+    pub reason: String,
+}
+
+/// Parse a list like `data/unobtainable.txt`: a `version 1` line, then `slot | name | reason`
+/// lines. Blank lines and lines starting with `#` are skipped.
+pub fn parse_unobtainable(text: &str) -> Result<Vec<Unobtainable>, String> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(number, line)| (number + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+    match lines.next() {
+        Some((_, line)) if line == format!("version {UNOBTAINABLE_VERSION}") => {}
+        Some((number, line)) => {
+            return Err(format!(
+                "line {number}: expected version {UNOBTAINABLE_VERSION}, got {line}"
+            ))
+        }
+        None => return Err("missing version line".to_string()),
+    }
+    let mut list: Vec<Unobtainable> = Vec::new();
+    for (number, line) in lines {
+        let fields: Vec<&str> = line.split('|').map(str::trim).collect();
+        let [slot, name, reason] = fields[..] else {
+            return Err(format!("line {number}: expected slot | name | reason"));
+        };
+        let Some(slot) = slot_named(slot) else {
+            return Err(format!("line {number}: {slot} is not a slot"));
+        };
+        list.push(Unobtainable {
+            slot,
+            name: name.to_lowercase(),
+            reason: reason.to_string(),
+        });
+    }
+    Ok(list)
+}
+
+/// The built-in list of unobtainable pieces, from `data/unobtainable.txt`.
+#[must_use]
+pub fn unobtainable() -> Vec<Unobtainable> {
+    parse_unobtainable(UNOBTAINABLE).expect("data/unobtainable.txt is broken")
+}
+
+/// Drop the pieces in `list` from `pieces`, matching slot and exact name. Returns the entries
+/// that matched a piece, and the stale ones that matched none, like after the wiki renamed it.
+pub fn remove_unobtainable<'a>(
+    pieces: &mut Vec<ArmorPiece>,
+    list: &'a [Unobtainable],
+) -> (Vec<&'a Unobtainable>, Vec<&'a Unobtainable>) {
+    let matches = |piece: &ArmorPiece, entry: &Unobtainable| {
+        piece.slot == entry.slot && piece.name == entry.name
+    };
+    let (removed, stale): (Vec<&Unobtainable>, Vec<&Unobtainable>) = list
+        .iter()
+        .partition(|entry| pieces.iter().any(|piece| matches(piece, entry)));
+    pieces.retain(|piece| !list.iter().any(|entry| matches(piece, entry)));
+    (removed, stale)
+}
+
+/// What `adjust_pieces` changed in the parsed pieces, for the caller to show.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Adjustments {
+    /// Overrides that were applied.
+    pub applied: Vec<Override>,
+    /// Overrides that couldn't be applied, see `Override::warning()`.
+    pub unmatched: Vec<Override>,
+    /// Unobtainable pieces that were left out.
+    pub removed: Vec<Unobtainable>,
+    /// Entries of the unobtainable list that match no piece.
+    pub stale: Vec<Unobtainable>,
+}
+
+impl Adjustments {
+    /// A line for each thing worth telling the user, warnings prefixed with `warning:`.
+    #[must_use]
+    pub fn messages(&self) -> Vec<String> {
+        let mut messages: Vec<String> = Vec::new();
+        for entry in &self.applied {
+            messages.push(format!("applied override {entry}"));
+        }
+        for entry in &self.unmatched {
+            messages.push(format!("warning: {}", entry.warning()));
+        }
+        if !self.removed.is_empty() {
+            messages.push(format!(
+                "pieces left out as unobtainable: {}",
+                self.removed.len()
+            ));
+        }
+        for entry in &self.stale {
+            messages.push(format!(
+                "warning: unobtainable {} {} matches no piece",
+                entry.slot, entry.name
+            ));
+        }
+        messages
+    }
+}
+
+/// Apply the user's `overrides`, keeping `maximize_stat` up to date, then leave out the
+/// unobtainable pieces unless `include_unobtainable`, and the Shadow of the Erdtree pieces unless
+/// `include_dlc`. Returns what was changed.
+pub fn adjust_pieces(
+    pieces: &mut Vec<ArmorPiece>,
+    overrides: &[Override],
+    maximize_stat: usize,
+    include_dlc: bool,
+    include_unobtainable: bool,
+) -> Adjustments {
+    let mut adjustments = Adjustments::default();
+    let (applied, unmatched) = apply_overrides(pieces, overrides, maximize_stat);
+    adjustments.applied = applied.into_iter().cloned().collect();
+    adjustments.unmatched = unmatched.into_iter().cloned().collect();
+    // The whole list is checked before the DLC pieces go, so none of it looks stale.
+    if !include_unobtainable {
+        let list = unobtainable();
+        let (removed, stale) = remove_unobtainable(pieces, &list);
+        adjustments.removed = removed.into_iter().cloned().collect();
+        adjustments.stale = stale.into_iter().cloned().collect();
+    }
+    filter_dlc(pieces, include_dlc);
+    adjustments
+}
+
+/// What an override does to the piece it names.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum OverrideKind {
+    /// Replace some of the stats of a listed piece.
+    Patch,
+    /// List a piece the wiki is missing.
+    Add,
+    /// Drop a piece the wiki lists wrongly.
+    Remove,
+}
+
+/// A user's correction to the pieces parsed from the wiki.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Override {
+    /// Line of the override in its file, for reports.
+    pub line: usize,
+    pub kind: OverrideKind,
+    pub slot: Slot,
+    /// The piece's name in lowercase, like parsed names.
+    pub name: String,
+    /// New values of the stats in `STATS` order, in tenths. Stats left out keep their value, or
+    /// are 0 for added pieces.
+    pub stats: [Option<u16>; 13],
+    pub weight: Option<u16>,
+}
+
+impl Override {
+    /// Warning for an override `apply_overrides` could not apply.
+    pub fn warning(&self) -> String {
+        match self.kind {
+            OverrideKind::Add => format!("override {self} adds a piece that is already listed"),
+            _ => format!("override {self} matches no piece"),
+        }
+    }
+}
+
+impl std::fmt::Display for Override {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let kind = format!("{:?}", self.kind).to_lowercase();
+        write!(f, "line {}: {kind} {} {}", self.line, self.slot, self.name)
+    }
+}
+
+/// Highest value an override can give a stat or weight, in tenths. A set sums eight negations
+/// over four pieces for the negation average, and that has to fit in a u16.
+const MAX_OVERRIDE: u16 = 2000;
+
+/// Parse overrides, one per line: `patch | helm | cleanrot helm | poise=9 weight=6.4`,
+/// `add | chest | name | physical=5 ... weight=10` or `remove | legs | name`. Values are in the
+/// units sets are displayed in, up to 200. Blank lines and lines starting with `#` are skipped.
+pub fn parse_overrides(text: &str) -> Result<Vec<Override>, String> {
+    let mut overrides: Vec<Override> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let number = number + 1;
+        let fields: Vec<&str> = line.split('|').map(str::trim).collect();
+        let keyword = fields[0].to_lowercase();
+        let (kind, slot, name, values) = match (keyword.as_str(), &fields[1..]) {
+            ("patch", &[slot, name, values]) => (OverrideKind::Patch, slot, name, values),
+            ("add", &[slot, name, values]) => (OverrideKind::Add, slot, name, values),
+            ("remove", &[slot, name]) => (OverrideKind::Remove, slot, name, ""),
+            _ => {
+                return Err(format!(
+                    "line {number}: expected patch | slot | name | stat=value ..., \
+                     add | slot | name | stat=value ... or remove | slot | name"
+                ))
+            }
+        };
+        let Some(slot) = slot_named(slot) else {
+            return Err(format!("line {number}: {slot} is not a slot"));
+        };
+        let mut stats = [None; 13];
+        let mut weight = None;
+        for value in values.split_whitespace() {
+            let Some((key, value)) = value.split_once('=') else {
+                return Err(format!("line {number}: expected stat=value, got {value}"));
+            };
+            let Ok(number_value) = value.parse::<f32>() else {
+                return Err(format!("line {number}: {value} is not a number"));
+            };
+            // Values are kept in tenths.
+            if !number_value.is_finite()
+                || number_value < 0.0
+                || number_value * 10.0 > f32::from(MAX_OVERRIDE)
+            {
+                return Err(format!(
+                    "line {number}: {key}={value} is out of range, expected 0 to {}",
+                    f32::from(MAX_OVERRIDE) / 10.0
+                ));
+            }
+            let value = Some((number_value * 10.0).round() as u16);
+            match STATS[..13].iter().position(|x| *x == key) {
+                Some(index) => stats[index] = value,
+                None if key == "weight" => weight = value,
+                None => return Err(format!("line {number}: {key} is not a stat")),
+            }
+        }
+        if kind == OverrideKind::Patch && weight.is_none() && stats.iter().all(Option::is_none) {
+            return Err(format!("line {number}: patch changes nothing"));
+        }
+        overrides.push(Override {
+            line: number,
+            kind,
+            slot,
+            name: name.to_lowercase(),
+            stats,
+            weight,
+        });
+    }
+    Ok(overrides)
+}
+
+/// Load the user's overrides from `overrides.txt` in the config directory. A missing file means
+/// no overrides.
+pub fn load_overrides() -> Result<Vec<Override>, String> {
+    let Ok(xdg_dirs) = xdg::BaseDirectories::with_prefix("elden_boc") else {
+        return Err("could not get xdg directories".to_string());
+    };
+    let Some(path) = xdg_dirs.find_config_file("overrides.txt") else {
+        return Ok(Vec::new());
+    };
+    let text = std::fs::read_to_string(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+    parse_overrides(&text).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// Apply `overrides` to `pieces`, keeping `maximize_stat` up to date. Returns the overrides that
+/// were applied, and those that weren't: patches and removals that match no piece, and
+/// additions of pieces that are already listed.
+pub fn apply_overrides<'a>(
+    pieces: &mut Vec<ArmorPiece>,
+    overrides: &'a [Override],
+    maximize_stat: usize,
+) -> (Vec<&'a Override>, Vec<&'a Override>) {
+    let mut applied: Vec<&Override> = Vec::new();
+    let mut unmatched: Vec<&Override> = Vec::new();
+    for entry in overrides {
+        let matches = |piece: &ArmorPiece| piece.slot == entry.slot && piece.name == entry.name;
+        let found = pieces.iter().any(matches);
+        if found == (entry.kind == OverrideKind::Add) {
+            unmatched.push(entry);
+            continue;
+        }
+        match entry.kind {
+            OverrideKind::Remove => pieces.retain(|piece| !matches(piece)),
+            OverrideKind::Patch | OverrideKind::Add => {
+                if entry.kind == OverrideKind::Add {
+                    let mut piece = ArmorPiece::new(entry.slot.clone());
+                    piece.name.push_str(&entry.name);
+                    piece.section.push_str("overrides");
+                    piece.dlc = is_dlc(&piece.name, &piece.section);
+                    pieces.push(piece);
+                }
+                for piece in pieces.iter_mut().filter(|piece| matches(piece)) {
+                    for (index, value) in entry.stats.iter().enumerate() {
+                        if let Some(value) = value {
+                            set_piece_stat(piece, index, *value);
+                        }
+                    }
+                    if let Some(weight) = entry.weight {
+                        piece.weight = weight;
+                    }
+                    piece.maximize_stat = piece.total(maximize_stat);
+                }
+            }
+        }
+        applied.push(entry);
+    }
+    (applied, unmatched)
+}
+
+/// Set the base stat at `index` in `STATS` of `piece`.
+fn set_piece_stat(piece: &mut ArmorPiece, index: usize, value: u16) {
+    match index {
+        0 => piece.physical = value,
+        1 => piece.strike = value,
+        2 => piece.slash = value,
+        3 => piece.pierce = value,
+        4 => piece.magic = value,
+        5 => piece.fire = value,
+        6 => piece.lightning = value,
+        7 => piece.holy = value,
+        8 => piece.immunity = value,
+        9 => piece.robustness = value,
+        10 => piece.focus = value,
+        11 => piece.vitality = value,
+        12 => piece.poise = value,
+        _ => {}
+    }
+}
+
+/// Parse `key = value` settings, one per line. Blank lines and lines starting with `#` are
+/// skipped.
+pub fn parse_config(text: &str) -> Result<HashMap<String, String>, String> {
+    let mut config: HashMap<String, String> = HashMap::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!("line {}: expected key = value", number + 1));
+        };
+        config.insert(key.trim().to_lowercase(), value.trim().to_string());
+    }
+    Ok(config)
+}
+
+/// Load the user's settings from `config.txt` in the config directory. A missing file means
+/// the defaults.
+pub fn load_config() -> Result<HashMap<String, String>, String> {
+    let Ok(xdg_dirs) = xdg::BaseDirectories::with_prefix("elden_boc") else {
+        return Err("could not get xdg directories".to_string());
+    };
+    let Some(path) = xdg_dirs.find_config_file("config.txt") else {
+        return Ok(HashMap::new());
+    };
+    let text = std::fs::read_to_string(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+    parse_config(&text).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// How the wiki pages are fetched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpConfig {
+    /// Limit on a whole request, including reading the page.
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+    /// Attempts after the first one, for connection errors and server errors.
+    pub retries: u32,
+    /// Wait before the first retry, doubled for each one after.
+    pub backoff: Duration,
+    pub user_agent: String,
+    /// Proxy for every request. Without one, the `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY`
+    /// environment variables apply.
+    pub proxy: Option<String>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(10),
+            retries: 3,
+            backoff: Duration::from_millis(500),
+            user_agent: format!(
+                "elden_boc/{} (+https://github.com/cyberrumor/elden_ring_build_optimizer)",
+                env!("CARGO_PKG_VERSION")
+            ),
+            proxy: None,
+        }
+    }
+}
+
+impl HttpConfig {
+    /// The defaults, overridden by the `timeout`, `connect_timeout` (seconds), `retries`,
+    /// `backoff_ms`, `user_agent` and `proxy` settings in `config`.
+    pub fn from_config(config: &HashMap<String, String>) -> Result<Self, String> {
+        let mut http = Self::default();
+        let number = |key: &str| -> Result<Option<u64>, String> {
+            config
+                .get(key)
+                .map(|x| x.parse().map_err(|_| format!("{key}: {x} is not a number")))
+                .transpose()
+        };
+        if let Some(seconds) = number("timeout")? {
+            http.timeout = Duration::from_secs(seconds);
+        }
+        if let Some(seconds) = number("connect_timeout")? {
+            http.connect_timeout = Duration::from_secs(seconds);
+        }
+        if let Some(retries) = number("retries")? {
+            http.retries =
+                u32::try_from(retries).map_err(|_| format!("retries: {retries} is too many"))?;
+        }
+        if let Some(millis) = number("backoff_ms")? {
+            http.backoff = Duration::from_millis(millis);
+        }
+        if let Some(user_agent) = config.get("user_agent") {
+            http.user_agent.clone_from(user_agent);
+        }
+        http.proxy = config.get("proxy").filter(|x| !x.is_empty()).cloned();
+        Ok(http)
+    }
+}
+
+/// An HTTP client that retries failed requests.
+#[derive(Debug, Clone)]
+pub struct Fetcher {
+    client: reqwest::blocking::Client,
+    retries: u32,
+    backoff: Duration,
+}
+
+impl Fetcher {
+    pub fn new(http: &HttpConfig) -> Result<Self, String> {
+        let mut builder = reqwest::blocking::Client::builder()
+            .timeout(http.timeout)
+            .connect_timeout(http.connect_timeout)
+            .user_agent(&http.user_agent);
+        if let Some(proxy) = &http.proxy {
+            let proxy = reqwest::Proxy::all(proxy).map_err(|e| format!("proxy {proxy}: {e}"))?;
+            builder = builder.proxy(proxy);
+        }
+        let client = builder
+            .build()
+            .map_err(|e| format!("could not build http client: {e}"))?;
+        Ok(Self {
+            client,
+            retries: http.retries,
+            backoff: http.backoff,
+        })
+    }
+
+    /// Send the request `build` makes, again after a growing wait while it fails to connect,
+    /// times out, or the server has trouble or asks to slow down. Other errors, like a bad URL,
+    /// won't go away by retrying.
+    fn send(
+        &self,
+        build: impl Fn(&reqwest::blocking::Client) -> reqwest::blocking::RequestBuilder,
+    ) -> reqwest::Result<reqwest::blocking::Response> {
+        let mut attempt = 0;
+        loop {
+            let result = build(&self.client).send();
+            let retry = match &result {
+                Ok(resp) => {
+                    resp.status().is_server_error()
+                        || resp.status() == reqwest::StatusCode::TOO_MANY_REQUESTS
+                }
+                Err(e) => e.is_connect() || e.is_timeout(),
+            };
+            if !retry || attempt >= self.retries {
+                return result;
+            }
+            thread::sleep(self.backoff * 2_u32.saturating_pow(attempt));
+            attempt += 1;
+        }
+    }
+}
+
+/// The armor slots that have wiki pages, in set order.
+pub const SLOTS: [Slot; 4] = [Slot::Helm, Slot::Chest, Slot::Gauntlets, Slot::Legs];
+
+/// The slot in `SLOTS` called `name`, in any case, e.g. `helm`.
+#[must_use]
+pub fn slot_named(name: &str) -> Option<Slot> {
+    SLOTS
+        .iter()
+        .find(|x| x.to_string().eq_ignore_ascii_case(name))
+        .cloned()
+}
+
+/// The cache directory: `dir` if given, else `~/.cache/elden_boc`. Either is created if missing.
+pub fn cache_dir(dir: Option<&Path>) -> Result<PathBuf, String> {
+    match dir {
+        Some(dir) => std::fs::create_dir_all(dir)
+            .map(|()| dir.to_path_buf())
+            .map_err(|e| format!("unable to create cache directory {}: {e}", dir.display())),
+        None => {
+            let Ok(xdg_dirs) = xdg::BaseDirectories::with_prefix("elden_boc") else {
+                return Err("could not get xdg directories!".to_string());
+            };
+            xdg_dirs
+                .create_cache_directory("")
+                .map_err(|e| format!("unable to create cache directory: {e}"))
+        }
+    }
+}
+
+/// Where the wiki page of `slot` is cached in `cache_dir`.
+#[must_use]
+pub fn cache_file(slot: &Slot, cache_dir: &Path) -> PathBuf {
+    cache_dir.join(format!("{slot}.html"))
+}
+
+/// Where the parsed pieces of `slot` are cached in `cache_dir`, next to the page.
+#[must_use]
+pub fn parsed_file(slot: &Slot, cache_dir: &Path) -> PathBuf {
+    cache_dir.join(format!("{slot}.pieces"))
+}
+
+/// 64-bit FNV-1a hash of a page, to tell whether parsed pieces came from it.
+#[must_use]
+pub fn source_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Cache `pieces`, parsed from the page of `slot` with hash `hash`. Each piece is a line of its
+/// name, its section and then its stats and weight in tenths, separated by tabs.
+#[must_use]
+pub fn save_parsed(slot: &Slot, cache_dir: &Path, hash: u64, pieces: &[ArmorPiece]) -> bool {
+    let mut text = format!("{PARSED_HEADER} {PARSER_VERSION} {hash:016x}\n");
+    for piece in pieces {
+        text.push_str(&piece.name.replace(['\t', '\n'], " "));
+        text.push('\t');
+        text.push_str(&piece.section.replace(['\t', '\n'], " "));
+        for index in 0..13 {
+            text.push_str(&format!("\t{}", piece.stat(index)));
+        }
+        text.push_str(&format!("\t{}\n", piece.weight));
+    }
+    std::fs::write(parsed_file(slot, cache_dir), text).is_ok()
+}
+
+/// The cached pieces of `slot`, if they were parsed by this parser version from the page with
+/// hash `hash`.
+#[must_use]
+pub fn load_parsed(
+    slot: &Slot,
+    cache_dir: &Path,
+    hash: u64,
+    maximize_stat: usize,
+) -> Option<Vec<ArmorPiece>> {
+    let text = std::fs::read_to_string(parsed_file(slot, cache_dir)).ok()?;
+    let mut lines = text.lines();
+    if lines.next()? != format!("{PARSED_HEADER} {PARSER_VERSION} {hash:016x}") {
+        return None;
+    }
+    let mut pieces: Vec<ArmorPiece> = Vec::new();
+    for line in lines {
+        let mut fields = line.split('\t');
+        let mut piece = ArmorPiece::new(slot.clone());
+        piece.name.push_str(fields.next()?);
+        piece.section.push_str(fields.next()?);
+        piece.dlc = is_dlc(&piece.name, &piece.section);
+        let numbers: Vec<u16> = fields.map(str::parse).collect::<Result<_, _>>().ok()?;
+        let numbers: [u16; 14] = numbers.try_into().ok()?;
+        piece.physical = numbers[0];
+        piece.strike = numbers[1];
+        piece.slash = numbers[2];
+        piece.pierce = numbers[3];
+        piece.magic = numbers[4];
+        piece.fire = numbers[5];
+        piece.lightning = numbers[6];
+        piece.holy = numbers[7];
+        piece.immunity = numbers[8];
+        piece.robustness = numbers[9];
+        piece.focus = numbers[10];
+        piece.vitality = numbers[11];
+        piece.poise = numbers[12];
+        piece.weight = numbers[13];
+        piece.maximize_stat = piece.total(maximize_stat);
+        pieces.push(piece);
+    }
+    Some(pieces)
+}
+
+/// The pieces on the page `text` of `slot`, from the parsed cache if it was made from this page,
+/// else parsed and cached for next time.
+///
+/// # Errors
+///
+/// Returns an error if the page has no armor table.
+pub fn get_slot_pieces(
+    slot: &Slot,
+    text: &str,
+    cache_dir: &Path,
+    maximize_stat: usize,
+) -> Result<Vec<ArmorPiece>, String> {
+    let hash = source_hash(text);
+    if let Some(pieces) = load_parsed(slot, cache_dir, hash, maximize_stat) {
+        return Ok(pieces);
+    }
+    let pieces = try_pieces_from_text(slot, text, maximize_stat)?;
+    if !save_parsed(slot, cache_dir, hash, &pieces) {
+        println!("could not save {slot}.pieces");
+    }
+    Ok(pieces)
+}
+
+/// Response headers that tell the wiki whether a page changed since it was fetched.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// Where the validators of the cached page of `slot` are kept in `cache_dir`.
+#[must_use]
+pub fn validators_file(slot: &Slot, cache_dir: &Path) -> PathBuf {
+    cache_dir.join(format!("{slot}.validators"))
+}
+
+/// Keep the validators the page of `slot` was fetched with, as `etag: ...` and
+/// `last-modified: ...` lines.
+#[must_use]
+pub fn save_validators(slot: &Slot, cache_dir: &Path, validators: &Validators) -> bool {
+    let mut text = String::new();
+    if let Some(etag) = &validators.etag {
+        text.push_str(&format!("etag: {etag}\n"));
+    }
+    if let Some(last_modified) = &validators.last_modified {
+        text.push_str(&format!("last-modified: {last_modified}\n"));
+    }
+    std::fs::write(validators_file(slot, cache_dir), text).is_ok()
+}
+
+/// The validators the cached page of `slot` was fetched with, empty if there are none.
+#[must_use]
+pub fn load_validators(slot: &Slot, cache_dir: &Path) -> Validators {
+    let mut validators = Validators::default();
+    let Ok(text) = std::fs::read_to_string(validators_file(slot, cache_dir)) else {
+        return validators;
+    };
+    for line in text.lines() {
+        match line.split_once(": ") {
+            Some(("etag", etag)) => validators.etag = Some(etag.to_string()),
+            Some(("last-modified", date)) => validators.last_modified = Some(date.to_string()),
+            _ => {}
+        }
+    }
+    validators
+}
+
+/// A cached wiki page.
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    pub slot: Slot,
+    /// Size of the page in bytes.
+    pub size: u64,
+    /// When the page was fetched, going by the file's modification time.
+    pub fetched: std::time::SystemTime,
+}
+
+/// The cached pages in `cache_dir`, in `SLOTS` order. Slots that aren't cached are left out.
+#[must_use]
+pub fn cache_entries(cache_dir: &Path) -> Vec<CacheEntry> {
+    SLOTS
+        .iter()
+        .filter_map(|slot| {
+            let metadata = std::fs::metadata(cache_file(slot, cache_dir)).ok()?;
+            Some(CacheEntry {
+                slot: slot.clone(),
+                size: metadata.len(),
+                fetched: metadata.modified().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// Fetch the page of `slot` again and replace its cached copy, returning whether it changed. If
+/// `conditional`, the wiki is asked for the page only if it changed since it was cached.
+pub fn refresh_cache(
+    slot: &Slot,
+    cache_dir: &Path,
+    urls: &HashMap<String, String>,
+    fetcher: &Fetcher,
+    conditional: bool,
+) -> Result<bool, String> {
+    let cached = load_from_file(slot, cache_dir);
+    let validators = if conditional && cached.is_some() {
+        load_validators(slot, cache_dir)
+    } else {
+        Validators::default()
+    };
+    let fetched = fetch_page(slot, urls, fetcher, &validators)
+        .map_err(|e| format!("could not read {slot} data from web: {e}"))?;
+    let Some((text, validators)) = fetched else {
+        return Ok(false);
+    };
+    validate_page(slot, &text).map_err(|e| format!("not caching {slot}.html: {e}"))?;
+    let changed = cached.as_ref() != Some(&text);
+    if changed && !save_to_file(slot, cache_dir, &text) {
+        return Err(format!("could not save {slot}.html"));
+    }
+    if !save_validators(slot, cache_dir, &validators) {
+        return Err(format!("could not save {slot}.validators"));
+    }
+    Ok(changed)
+}
+
+/// Refresh the pages of all `slots` at the same time, like `refresh_cache`. Returns each slot
+/// with whether its page changed, or why it couldn't be refreshed.
+#[must_use]
+pub fn refresh_caches(
+    slots: &[Slot],
+    cache_dir: &Path,
+    urls: &HashMap<String, String>,
+    fetcher: &Fetcher,
+    conditional: bool,
+) -> Vec<(Slot, Result<bool, String>)> {
+    thread::scope(|scope| {
+        let handles: Vec<_> = slots
+            .iter()
+            .map(|slot| {
+                scope.spawn(move || refresh_cache(slot, cache_dir, urls, fetcher, conditional))
+            })
+            .collect();
+        slots
+            .iter()
+            .cloned()
+            .zip(
+                handles
+                    .into_iter()
+                    .map(|x| x.join().expect("fetching thread panicked")),
+            )
+            .collect()
+    })
+}
+
+/// Delete every cached page, with its parsed pieces and validators, in `cache_dir`, returning how
+/// many pages there were.
+pub fn clear_cache(cache_dir: &Path) -> Result<usize, String> {
+    let mut removed = 0;
+    for slot in &SLOTS {
+        for file in [
+            parsed_file(slot, cache_dir),
+            validators_file(slot, cache_dir),
+        ] {
+            if file.exists() {
+                std::fs::remove_file(&file)
+                    .map_err(|e| format!("could not remove {}: {e}", file.display()))?;
+            }
+        }
+        let file = cache_file(slot, cache_dir);
+        if file.exists() {
+            std::fs::remove_file(&file)
+                .map_err(|e| format!("could not remove {}: {e}", file.display()))?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Check that the cached page of `slot` still parses, returning how many pieces it has.
+pub fn verify_cache(slot: &Slot, cache_dir: &Path) -> Result<usize, String> {
+    let Some(text) = load_from_file(slot, cache_dir) else {
+        return Err(format!("{slot}.html is not cached"));
+    };
+    validate_page(slot, &text)
+}
+
+#[must_use]
+pub fn load_from_file(slot: &Slot, cache_dir: &Path) -> Option<String> {
+    let file = cache_file(slot, cache_dir);
+    if Path::exists(&file) {
         match std::fs::read_to_string(file) {
             Ok(something) => {
                 return Some(something);
@@ -457,100 +3141,215 @@ pub fn load_from_file(slot: &Slot, xdg_dirs: &xdg::BaseDirectories) -> Option<St
 }
 
 #[must_use]
-pub fn save_to_file(slot: &Slot, xdg_dirs: &xdg::BaseDirectories, text: &String) -> bool {
-    let Ok(path) = xdg_dirs.place_cache_file(format!("{slot}.html")) else {
-        return false
-    };
-    let Ok(mut file) = File::create(path) else {
+pub fn save_to_file(slot: &Slot, cache_dir: &Path, text: &String) -> bool {
+    let Ok(mut file) = File::create(cache_file(slot, cache_dir)) else {
         return false
     };
     write!(file, "{text}").is_ok()
 }
 
-#[must_use]
+/// Fetch the wiki page of `slot`, failing on an error status or a response that isn't HTML.
 pub fn load_from_web(
     slot: &Slot,
     urls: &HashMap<String, String>,
-    client: &reqwest::blocking::Client,
-) -> Option<String> {
+    fetcher: &Fetcher,
+) -> Result<(String, Validators), String> {
+    fetch_page(slot, urls, fetcher, &Validators::default())?
+        .ok_or_else(|| format!("{slot} page came back unchanged without being asked"))
+}
+
+/// Like `load_from_web`, but `None` if the page hasn't changed since it was fetched with
+/// `validators`.
+pub fn fetch_page(
+    slot: &Slot,
+    urls: &HashMap<String, String>,
+    fetcher: &Fetcher,
+    validators: &Validators,
+) -> Result<Option<(String, Validators)>, String> {
     let slot_string = slot.to_string();
     let url = urls.get(&slot_string).expect("couldn't find url");
 
-    let request = client.get(url).send();
+    let request = |client: &reqwest::blocking::Client| {
+        let mut request = client.get(url);
+        if let Some(etag) = &validators.etag {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+        }
+        request
+    };
+
+    let resp = fetcher
+        .send(request)
+        .map_err(|e| format!("could not fetch {url}: {e}"))?;
+    let status = resp.status();
+    if status == reqwest::StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+    if !status.is_success() {
+        return Err(format!("{url} returned HTTP {status}"));
+    }
+    let header = |name| {
+        resp.headers()
+            .get(name)
+            .and_then(|x| x.to_str().ok())
+            .map(str::to_string)
+    };
+    let content_type = header(reqwest::header::CONTENT_TYPE).unwrap_or_default();
+    if !content_type.to_ascii_lowercase().starts_with("text/html") {
+        return Err(format!("{url} returned {content_type:?} instead of a page"));
+    }
+    let fetched = Validators {
+        etag: header(reqwest::header::ETAG),
+        last_modified: header(reqwest::header::LAST_MODIFIED),
+    };
+    let text = resp
+        .text()
+        .map_err(|e| format!("could not read {url}: {e}"))?;
+    Ok(Some((text, fetched)))
+}
 
-    let Ok(resp) = request else { return None };
-    let Ok(body) = resp.text() else { return None};
-    Some(body)
+/// Check that `text` is a wiki page listing the pieces of `slot`, rather than an error,
+/// challenge or maintenance page, returning how many pieces it has.
+pub fn validate_page(slot: &Slot, text: &str) -> Result<usize, String> {
+    let pieces = try_pieces_from_text(slot, text, 0)?;
+    let named = pieces.iter().filter(|x| !x.name.is_empty()).count();
+    if named < MIN_PAGE_PIECES {
+        return Err(format!(
+            "{slot} page lists {named} pieces, expected at least {MIN_PAGE_PIECES}"
+        ));
+    }
+    Ok(pieces.len())
 }
 
-#[must_use]
-pub fn get_text_or_die(
+/// The page of `slot` from the cache, or else fetched and cached.
+pub fn get_text(
     slot: &Slot,
-    xdg_dirs: &xdg::BaseDirectories,
+    cache_dir: &Path,
     urls: &HashMap<String, String>,
-    client: &reqwest::blocking::Client,
-) -> String {
-    let text = load_from_file(slot, xdg_dirs).map_or_else(
-        || {
-            println!("Fetching {slot} data from web");
-            load_from_web(slot, urls, client).map_or_else(
-                || {
-                    println!("could not read {slot} data from web");
-                    std::process::exit(1);
-                },
-                |file| file,
-            )
-        },
-        |file| file,
-    );
-    if xdg_dirs.get_cache_file(format!("{slot}.html")).exists() {
-        return text;
+    fetcher: &Fetcher,
+) -> Result<String, String> {
+    if let Some(text) = load_from_file(slot, cache_dir) {
+        return Ok(text);
     }
-    if save_to_file(slot, xdg_dirs, &text) {
-        println!("successfully cached {slot}.html");
-    } else {
-        println!("could not save {slot}.html");
-        std::process::exit(1);
+    println!("Fetching {slot} data from web");
+    let (text, validators) = load_from_web(slot, urls, fetcher)
+        .and_then(|(text, validators)| validate_page(slot, &text).map(|_| (text, validators)))
+        .map_err(|e| format!("could not read {slot} data from web: {e}"))?;
+    if !save_to_file(slot, cache_dir, &text) || !save_validators(slot, cache_dir, &validators) {
+        return Err(format!("could not save {slot}.html"));
     }
-    text
+    println!("successfully cached {slot}.html");
+    Ok(text)
 }
 
+/// Where the wiki pages live, unless the `base_url` setting says otherwise.
+pub const WIKI_BASE_URL: &str = "https://eldenring.wiki.fextralife.com";
+
+/// Path under the base URL of the page listing every piece of each slot.
+const WIKI_PAGES: [(&str, &str); 4] = [
+    ("Helm", "Helms"),
+    ("Chest", "Chest+Armor"),
+    ("Gauntlets", "Gauntlets"),
+    ("Legs", "Leg+Armor"),
+];
+
+/// The Fextralife wiki page listing every piece of each slot, keyed by the slot's name.
 #[must_use]
-pub fn get_pieces(maximize_stat: usize) -> Vec<ArmorPiece> {
-    let Ok(xdg_dirs) = xdg::BaseDirectories::with_prefix("elden_boc") else {
-        println!("could not get xdg directories!");
-        std::process::exit(1);
-    };
-    if xdg_dirs.create_cache_directory("").is_err() {
-        println!("unable to create cache directory");
-        std::process::exit(1);
-    };
-    let mut urls: HashMap<String, String> = HashMap::new();
-    urls.insert(
-        "Helm".to_string(),
-        "https://eldenring.wiki.fextralife.com/Helms".to_string(),
-    );
-    urls.insert(
-        "Chest".to_string(),
-        "https://eldenring.wiki.fextralife.com/Chest+Armor".to_string(),
-    );
-    urls.insert(
-        "Gauntlets".to_string(),
-        "https://eldenring.wiki.fextralife.com/Gauntlets".to_string(),
-    );
-    urls.insert(
-        "Legs".to_string(),
-        "https://eldenring.wiki.fextralife.com/Leg+Armor".to_string(),
+pub fn wiki_urls() -> HashMap<String, String> {
+    wiki_urls_at(WIKI_BASE_URL)
+}
+
+/// Like `wiki_urls`, on a mirror of the wiki at `base_url`.
+#[must_use]
+pub fn wiki_urls_at(base_url: &str) -> HashMap<String, String> {
+    let base_url = base_url.trim_end_matches('/');
+    WIKI_PAGES
+        .iter()
+        .map(|(slot, page)| (slot.to_string(), format!("{base_url}/{page}")))
+        .collect()
+}
+
+/// The page URLs from the `base_url` setting in `config`, then replaced one by one by settings
+/// like `helm_url` or `legs_url`.
+pub fn urls_from_config(
+    config: &HashMap<String, String>,
+) -> Result<HashMap<String, String>, String> {
+    let mut urls = wiki_urls_at(config.get("base_url").map_or(WIKI_BASE_URL, String::as_str));
+    for (key, url) in config {
+        let Some(slot) = key.strip_suffix("_url") else {
+            continue;
+        };
+        if slot == "base" {
+            continue;
+        }
+        let Some(slot) = slot_named(slot) else {
+            return Err(format!("{key}: {slot} is not a slot"));
+        };
+        urls.insert(slot.to_string(), url.clone());
+    }
+    Ok(urls)
+}
+
+/// The pieces as the wiki lists them, fetched with the default settings into the default cache
+/// directory. Nothing the user configures applies: not config.txt, overrides.txt or the
+/// unobtainable list.
+pub fn get_pieces(maximize_stat: usize) -> Result<Vec<ArmorPiece>, String> {
+    try_get_pieces_in(
+        &cache_dir(None)?,
+        maximize_stat,
+        &wiki_urls(),
+        &Fetcher::new(&HttpConfig::default())?,
+    )
+}
+
+/// Like `get_pieces`, but with the user's settings, overrides and lists applied, along with what
+/// they changed.
+pub fn try_get_pieces(maximize_stat: usize) -> Result<(Vec<ArmorPiece>, Adjustments), String> {
+    let cache_dir = cache_dir(None)?;
+    let config = load_config()?;
+    let fetcher = Fetcher::new(&HttpConfig::from_config(&config)?)?;
+    let mut pieces = try_get_pieces_in(
+        &cache_dir,
+        maximize_stat,
+        &urls_from_config(&config)?,
+        &fetcher,
+    )?;
+    let adjustments = adjust_pieces(
+        &mut pieces,
+        &load_overrides()?,
+        maximize_stat,
+        dlc_from_config(&config)?.unwrap_or(true),
+        switch_from_config(&config, "include_unobtainable")?.unwrap_or(false),
     );
+    Ok((pieces, adjustments))
+}
+
+/// Like `get_pieces`, caching the pages from `urls` in `cache_dir`. Pages that aren't cached yet
+/// are fetched at the same time.
+pub fn try_get_pieces_in(
+    cache_dir: &Path,
+    maximize_stat: usize,
+    urls: &HashMap<String, String>,
+    fetcher: &Fetcher,
+) -> Result<Vec<ArmorPiece>, String> {
+    let texts: Vec<Result<String, String>> = thread::scope(|scope| {
+        let handles: Vec<_> = SLOTS
+            .iter()
+            .map(|slot| scope.spawn(move || get_text(slot, cache_dir, urls, fetcher)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|x| x.join().expect("fetching thread panicked"))
+            .collect()
+    });
     let mut pieces: Vec<ArmorPiece> = Vec::new();
-    let slots: Vec<Slot> = vec![Slot::Helm, Slot::Chest, Slot::Gauntlets, Slot::Legs];
-    let client = reqwest::blocking::Client::new();
-    for slot in slots {
-        let text: String = get_text_or_die(&slot, &xdg_dirs, &urls, &client);
-        let slot_pieces: Vec<ArmorPiece> = get_pieces_from_text(&slot, &text, maximize_stat);
+    for (slot, text) in SLOTS.iter().zip(texts) {
+        let slot_pieces: Vec<ArmorPiece> = get_slot_pieces(slot, &text?, cache_dir, maximize_stat)?;
         pieces.extend(slot_pieces);
-        let empty_piece = ArmorPiece::new(slot);
+        let empty_piece = ArmorPiece::new(slot.clone());
         pieces.push(empty_piece);
     }
-    pieces
+    Ok(pieces)
 }
@@ -1,6 +1,12 @@
 pub mod core;
 
 #[cfg(test)]
+// The original wiki tests are kept as they were written.
+#[allow(
+    clippy::cmp_owned,
+    clippy::explicit_auto_deref,
+    clippy::needless_borrow
+)]
 pub mod tests {
     use lazy_static::lazy_static;
     use super::core;
@@ -8,7 +14,142 @@ pub mod tests {
     const MAXIMIZE_STAT: usize = 0;
     const WEIGHT_RESTRICTION: u16 = 60;
     lazy_static! {
-        static ref PIECES: Vec<core::ArmorPiece> = core::get_pieces(MAXIMIZE_STAT);
+        static ref PIECES: Vec<core::ArmorPiece> = core::get_pieces(MAXIMIZE_STAT).unwrap();
+    }
+
+    /// Build a piece from its stats in `core::STATS` order, without touching the wiki.
+    fn piece(slot: core::Slot, name: &str, stats: [u16; 13], weight: u16) -> core::ArmorPiece {
+        let mut piece = core::ArmorPiece::new(slot);
+        piece.name = String::from(name);
+        piece.physical = stats[0];
+        piece.strike = stats[1];
+        piece.slash = stats[2];
+        piece.pierce = stats[3];
+        piece.magic = stats[4];
+        piece.fire = stats[5];
+        piece.lightning = stats[6];
+        piece.holy = stats[7];
+        piece.immunity = stats[8];
+        piece.robustness = stats[9];
+        piece.focus = stats[10];
+        piece.vitality = stats[11];
+        piece.poise = stats[12];
+        piece.weight = weight;
+        piece
+    }
+
+    /// A small offline catalogue: two helms and an empty piece for every slot.
+    fn small_pieces() -> Vec<core::ArmorPiece> {
+        vec![
+            piece(
+                core::Slot::Helm,
+                "magic hood",
+                [0, 0, 0, 0, 90, 10, 0, 0, 0, 0, 0, 0, 0],
+                20,
+            ),
+            piece(
+                core::Slot::Helm,
+                "even helm",
+                [0, 0, 0, 0, 40, 40, 0, 0, 0, 0, 0, 0, 0],
+                30,
+            ),
+            core::ArmorPiece::new(core::Slot::Helm),
+            core::ArmorPiece::new(core::Slot::Chest),
+            core::ArmorPiece::new(core::Slot::Gauntlets),
+            core::ArmorPiece::new(core::Slot::Legs),
+        ]
+    }
+
+    /// Index in `core::BUFFS` of the buff called `name`.
+    fn buff(name: &str) -> usize {
+        core::BUFFS.iter().position(|x| x.name == name).unwrap()
+    }
+
+    /// Index in `core::TALISMANS` of the talisman called `name`.
+    fn talisman(name: &str) -> usize {
+        core::TALISMANS.iter().position(|x| x.name == name).unwrap()
+    }
+
+    /// A cache directory of a test's own, removed with everything in it when dropped, so even a
+    /// failing test cleans up.
+    struct TempCache(std::path::PathBuf);
+
+    impl TempCache {
+        fn new(name: &str) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("elden_boc_{name}_test_{}", std::process::id()));
+            Self(core::cache_dir(Some(&dir)).unwrap())
+        }
+    }
+
+    impl std::ops::Deref for TempCache {
+        type Target = std::path::Path;
+
+        fn deref(&self) -> &Self::Target {
+            &self.0
+        }
+    }
+
+    impl Drop for TempCache {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// An armor table of `rows` pieces named `name 0`, `name 1` and so on.
+    fn armor_table(name: &str, rows: usize) -> String {
+        let mut table = String::from("<table><thead><tr><th>Name</th></tr></thead><tbody>");
+        for row in 0..rows {
+            table.push_str(&format!("<tr><td>{name} {row}</td>"));
+            for stat in 1..14 {
+                table.push_str(&format!("<td>{stat}</td>"));
+            }
+            table.push_str("<td>4.5</td></tr>");
+        }
+        table.push_str("</tbody></table>");
+        table
+    }
+
+    /// A wiki page with an armor table of `rows` pieces.
+    fn wiki_page(rows: usize) -> String {
+        format!("<html><body>{}</body></html>", armor_table("Helm", rows))
+    }
+
+    /// An HTTP response with `status`, `headers` and `body`.
+    fn response(status: &str, headers: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {status}\r\n{headers}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )
+    }
+
+    /// Answer one HTTP request on a local port per response in `responses`, in order. Returns the
+    /// URL to request, and a handle that joins to the requests that came in.
+    fn serve_all(responses: Vec<String>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/page", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut requests: Vec<String> = Vec::new();
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = [0; 4096];
+                let length = stream.read(&mut request).unwrap();
+                stream.write_all(response.as_bytes()).unwrap();
+                requests.push(String::from_utf8_lossy(&request[..length]).to_lowercase());
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    /// Like `serve_all` with a single response.
+    fn serve(
+        status: &str,
+        headers: &str,
+        body: String,
+    ) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        serve_all(vec![response(status, headers, &body)])
     }
 
     #[test]
@@ -24,6 +165,9 @@ pub mod tests {
             // Cleanrot Helm has a unique number for each stat.
             name: String::from("cleanrot helm"),
             slot: core::Slot::Helm,
+            // The heading of the base game table on the wiki page.
+            section: String::from("Elden Ring Helms"),
+            dlc: false,
             physical: 52,
             slash: 58,
             strike: 48,
@@ -55,6 +199,8 @@ pub mod tests {
         let expected = core::ArmorPiece {
             name: String::from("cleanrot armor"),
             slot: core::Slot::Chest,
+            section: String::from("Elden Ring Chest Armor"),
+            dlc: false,
             physical: 146,
             slash: 160,
             strike: 134,
@@ -86,6 +232,8 @@ pub mod tests {
         let expected = core::ArmorPiece {
             name: String::from("cleanrot gauntlets"),
             slot: core::Slot::Gauntlets,
+            section: String::from("Elden Ring Gauntlets"),
+            dlc: false,
             physical: 36,
             slash: 40,
             strike: 33,
@@ -117,6 +265,8 @@ pub mod tests {
         let expected = core::ArmorPiece {
             name: String::from("cleanrot greaves"),
             slot: core::Slot::Legs,
+            section: String::from("Elden Ring Leg Armor"),
+            dlc: false,
             physical: 84,
             slash: 92,
             strike: 76,
@@ -214,4 +364,1112 @@ pub mod tests {
         println!("{}", result);
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn test_maximin_set() {
+        let (result, binding) = core::get_set_maximin(WEIGHT_RESTRICTION, small_pieces(), &[4, 5]);
+        assert_eq!(result.helm, "even helm");
+        assert_eq!(result.stat(binding), 40);
+
+        // Without the room for the even helm, the magic hood's fire is the weak point.
+        let (result, binding) = core::get_set_maximin(25, small_pieces(), &[4, 5]);
+        assert_eq!(result.helm, "magic hood");
+        assert_eq!(core::STATS[binding], "fire");
+    }
+
+    #[test]
+    fn test_ratio_set() {
+        let mut pieces = vec![
+            piece(core::Slot::Helm, "heavy helm", [0; 13], 50),
+            piece(core::Slot::Helm, "light helm", [0; 13], 20),
+            piece(core::Slot::Chest, "vest", [0; 13], 5),
+            core::ArmorPiece::new(core::Slot::Helm),
+            core::ArmorPiece::new(core::Slot::Chest),
+            core::ArmorPiece::new(core::Slot::Gauntlets),
+            core::ArmorPiece::new(core::Slot::Legs),
+        ];
+        for (piece, stat) in pieces.iter_mut().zip([100, 60, 30]) {
+            piece.maximize_stat = stat;
+        }
+
+        let (result, ratio) = core::get_set_ratio(WEIGHT_RESTRICTION, pieces.clone(), 0).unwrap();
+        assert_eq!((result.helm.as_str(), result.chest.as_str()), ("", "vest"));
+        assert!((ratio - 6.0).abs() < 1e-9);
+
+        let (result, ratio) = core::get_set_ratio(WEIGHT_RESTRICTION, pieces.clone(), 80).unwrap();
+        assert_eq!(
+            (result.helm.as_str(), result.chest.as_str()),
+            ("light helm", "vest")
+        );
+        assert!((ratio - 3.6).abs() < 1e-9);
+
+        assert!(core::get_set_ratio(WEIGHT_RESTRICTION, pieces, 200).is_none());
+    }
+
+    #[test]
+    fn test_near_optimal_set() {
+        let mut pieces = small_pieces();
+        for piece in &mut pieces {
+            piece.maximize_stat = piece.fire;
+        }
+        let (optimum, near) = core::get_set_near_optimal(WEIGHT_RESTRICTION, pieces.clone(), 90);
+        assert_eq!(optimum.helm, "even helm");
+        assert_eq!(near, optimum);
+
+        // A quarter of the even helm's fire is within reach of the lighter hood.
+        let (_, near) = core::get_set_near_optimal(WEIGHT_RESTRICTION, pieces, 25);
+        assert_eq!(near.helm, "magic hood");
+
+        // 30% of 10 is exactly 3, which the lightest helm reaches.
+        let mut pieces = vec![
+            piece(core::Slot::Helm, "best helm", [0; 13], 50),
+            piece(core::Slot::Helm, "light helm", [0; 13], 10),
+            piece(core::Slot::Helm, "middle helm", [0; 13], 20),
+            core::ArmorPiece::new(core::Slot::Chest),
+            core::ArmorPiece::new(core::Slot::Gauntlets),
+            core::ArmorPiece::new(core::Slot::Legs),
+        ];
+        for (piece, stat) in pieces.iter_mut().zip([10, 3, 4]) {
+            piece.maximize_stat = stat;
+        }
+        let (_, near) = core::get_set_near_optimal(WEIGHT_RESTRICTION, pieces, 30);
+        assert_eq!(near.helm, "light helm");
+    }
+
+    #[test]
+    fn test_composite_stats() {
+        let helm = piece(
+            core::Slot::Helm,
+            "helm",
+            [10, 20, 30, 40, 50, 50, 50, 50, 100, 200, 300, 400, 0],
+            10,
+        );
+        let chest = piece(
+            core::Slot::Chest,
+            "chest",
+            [1, 1, 1, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            10,
+        );
+        let set = core::ArmorSet::from(
+            helm,
+            chest,
+            core::ArmorPiece::new(core::Slot::Gauntlets),
+            core::ArmorPiece::new(core::Slot::Legs),
+        );
+        // 105 / 4 rounds to 26, although the pieces alone average to 25 and 1.
+        assert_eq!(set.stat(13), 26);
+        assert_eq!(set.stat(14), 50);
+        assert_eq!(set.stat(15), 38);
+        assert_eq!(set.stat(16), 1000);
+        assert!(set.to_string().contains("total resistances: 100\n"));
+
+        // Rounded per piece, both helms average 2 and the chests 2 and 1, so the lighter helm
+        // with the heavier chest looks best. The totals show the other pair is.
+        let mut pieces = vec![
+            piece(
+                core::Slot::Helm,
+                "six helm",
+                [2, 2, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+                10,
+            ),
+            piece(
+                core::Slot::Helm,
+                "nine helm",
+                [3, 2, 2, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+                20,
+            ),
+            piece(
+                core::Slot::Chest,
+                "six chest",
+                [2, 2, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+                20,
+            ),
+            piece(
+                core::Slot::Chest,
+                "five chest",
+                [2, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+                10,
+            ),
+            core::ArmorPiece::new(core::Slot::Gauntlets),
+            core::ArmorPiece::new(core::Slot::Legs),
+        ];
+        for piece in &mut pieces {
+            piece.maximize_stat = piece.total(13);
+        }
+        let result = core::get_set(30, pieces);
+        assert_eq!(result.helm, "nine helm");
+        assert_eq!(result.chest, "five chest");
+        assert_eq!(result.stat(13), 4);
+    }
+
+    #[test]
+    fn test_damage_profile_set() {
+        let profiles =
+            core::parse_profiles("# fire breathers\nflame: fire=90, magic=10\n").unwrap();
+        assert_eq!(profiles[0].to_string(), "flame: magic=10 fire=90");
+
+        let result =
+            core::get_set_damage_profile(WEIGHT_RESTRICTION, small_pieces(), &profiles[0], &[])
+                .unwrap();
+        assert_eq!(result.helm, "even helm");
+        assert!((profiles[0].damage_taken(&result) - 0.96).abs() < 1e-6);
+
+        assert!(core::parse_profiles("flame: frost=100").is_err());
+    }
+
+    #[test]
+    fn test_boss_presets() {
+        let bosses = core::bosses();
+        for query in ["malenia", "Malenia, Blade of Miquella", "malenai"] {
+            let boss = core::find_boss(&bosses, query).unwrap();
+            assert_eq!(boss.profile.name, "malenia, blade of miquella");
+        }
+        assert!(core::find_boss(&bosses, "the nameless eternal city")
+            .unwrap_err()
+            .is_empty());
+
+        // Both Radahns match equally well, and so do many bosses for a single letter.
+        let candidates = core::find_boss(&bosses, "radahn").unwrap_err();
+        let names: Vec<&str> = candidates.iter().map(|x| x.profile.name.as_str()).collect();
+        assert_eq!(names, ["starscourge radahn", "promised consort radahn"]);
+        assert!(core::find_boss(&bosses, "m").unwrap_err().is_empty());
+        let radahn = core::find_boss(&bosses, "starscourge radahn").unwrap();
+        assert_eq!(radahn.profile.name, "starscourge radahn");
+
+        let vyke = core::find_boss(&bosses, "vyke").unwrap();
+        let constraints = vyke.constraints(100);
+        assert_eq!(constraints, vec![core::Constraint { stat: 10, min: 100 }]);
+
+        let mut pieces = small_pieces();
+        pieces[0].focus = 100;
+        let result = core::get_set_damage_profile(
+            WEIGHT_RESTRICTION,
+            pieces.clone(),
+            &vyke.profile,
+            &constraints,
+        )
+        .unwrap();
+        assert_eq!(result.helm, "magic hood");
+        assert!(core::get_set_damage_profile(
+            WEIGHT_RESTRICTION,
+            pieces,
+            &vyke.profile,
+            &vyke.constraints(200)
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_equip_load() {
+        assert_eq!(core::max_equip_load(1), 450);
+        assert_eq!(core::max_equip_load(8), 450);
+        assert_eq!(core::max_equip_load(25), 720);
+        assert_eq!(core::max_equip_load(60), 1200);
+        assert_eq!(core::max_equip_load(99), 1600);
+
+        // 14.9 is 29.8% of 50, 15.0 would be exactly 30%.
+        assert_eq!(core::roll_budget(500, core::Roll::Light), 149);
+        assert_eq!(core::roll_budget(500, core::Roll::Medium), 349);
+        // Exactly the max still rolls heavy.
+        assert_eq!(core::roll_budget(1600, core::Roll::Heavy), 1600);
+        assert_eq!(core::roll_budget(500, core::Roll::Heavy), 500);
+        // 30% of 72.1 is 21.63, so 21.6 still rolls light.
+        assert_eq!(core::roll_budget(721, core::Roll::Light), 216);
+        // 70% of 72.1 is 50.47, and the heavy roll goes up to the max itself.
+        assert_eq!(core::roll_budget(721, core::Roll::Medium), 504);
+        assert_eq!(core::roll_budget(721, core::Roll::Heavy), 721);
+        // 70% of 60 is exactly 42.0, which already rolls heavy.
+        assert_eq!(core::roll_budget(600, core::Roll::Medium), 419);
+
+        assert_eq!(core::armor_budget(60, core::Roll::Medium, 300, &[]), 539);
+        assert_eq!(core::armor_budget(1, core::Roll::Light, 300, &[]), 0);
+    }
+
+    #[test]
+    fn test_equip_load_talismans() {
+        let jar = talisman("great-jar's arsenal");
+        let charm = talisman("arsenal charm");
+        assert_eq!(core::max_equip_load_with(60, &[jar]), 1428);
+        assert_eq!(core::max_equip_load_with(60, &[jar, charm]), 1642);
+        // 69.9% of 142.8 less 30 for weapons and 1.5 for the talisman.
+        assert_eq!(core::armor_budget(60, core::Roll::Medium, 300, &[jar]), 684);
+
+        let toggles = core::talisman_toggles(&[charm]);
+        assert_eq!(toggles[charm], (String::from("-arsenal charm"), vec![]));
+        assert_eq!(
+            toggles[charm + 1],
+            (
+                String::from("arsenal charm -> arsenal charm +1"),
+                vec![charm + 1]
+            )
+        );
+        assert_eq!(
+            toggles[jar],
+            (String::from("+great-jar's arsenal"), vec![charm, jar])
+        );
+    }
+
+    #[test]
+    fn test_set_with_talismans() {
+        let mut pieces = small_pieces();
+        pieces[0] = piece(core::Slot::Helm, "iron helm", [50; 13], 30);
+        for piece in &mut pieces {
+            piece.maximize_stat = piece.physical;
+        }
+        let available: Vec<usize> = (0..core::TALISMANS.len()).collect();
+        let name = |x: &usize| core::TALISMANS[*x].name;
+
+        // 13.4 of a light roll at Endurance 8, less 10 for weapons, leaves no room for the helm
+        // next to the shield talismans unless the arsenal talismans raise the load. The
+        // greatshield talisman stacks with the other one, and each lets through a share of what
+        // gets through the rest: 1 - 0.95 * 0.83 * 0.8.
+        let (result, talismans) = core::get_set_with_talismans(
+            8,
+            core::Roll::Light,
+            100,
+            pieces.clone(),
+            0,
+            &available,
+            4,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(result.helm, "iron helm");
+        assert_eq!(result.physical, 369);
+        assert_eq!(
+            talismans.iter().map(name).collect::<Vec<&str>>(),
+            vec![
+                "arsenal charm",
+                "great-jar's arsenal",
+                "dragoncrest shield talisman +2",
+                "dragoncrest greatshield talisman"
+            ]
+        );
+
+        // With two slots both shield talismans beat the helm.
+        let (result, talismans) = core::get_set_with_talismans(
+            8,
+            core::Roll::Light,
+            100,
+            pieces.clone(),
+            0,
+            &available,
+            2,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(result.helm, "");
+        assert_eq!(result.physical, 336);
+        assert_eq!(
+            talismans.iter().map(name).collect::<Vec<&str>>(),
+            vec![
+                "dragoncrest shield talisman +2",
+                "dragoncrest greatshield talisman"
+            ]
+        );
+
+        // With a single slot there's no charm, and the talisman beats the helm.
+        let (result, talismans) =
+            core::get_set_with_talismans(8, core::Roll::Light, 100, pieces, 0, &available, 1)
+                .unwrap()
+                .unwrap();
+        assert_eq!(result.helm, "");
+        assert_eq!(
+            talismans.iter().map(name).collect::<Vec<&str>>(),
+            vec!["dragoncrest greatshield talisman"]
+        );
+
+        // There's no stat to weigh the talismans against past the end of `STATS`.
+        assert!(core::get_set_with_talismans(
+            8,
+            core::Roll::Light,
+            100,
+            small_pieces(),
+            core::STATS.len(),
+            &available,
+            1
+        )
+        .is_err());
+
+        // 70% gets through the armor, and 80% of that through the talisman.
+        let greatshield = talisman("dragoncrest greatshield talisman");
+        let mut set = core::ArmorSet::new();
+        set.physical = 300;
+        assert_eq!(set.with_talismans(&[greatshield]).physical, 440);
+
+        let bull_goat = talisman("bull-goat's talisman");
+        let mut set = core::ArmorSet::new();
+        set.poise = 300;
+        assert_eq!(set.with_talismans(&[bull_goat]).poise, 399);
+    }
+
+    #[test]
+    fn test_buffs() {
+        assert!(core::check_buffs(&[buff("boiled crab"), buff("boiled prawn")]).is_err());
+        assert!(core::check_buffs(&[
+            buff("opaline hardtear"),
+            buff("opaline bubbletear (next hit only)")
+        ])
+        .is_err());
+        assert!(core::check_buffs(&[buff("boiled crab"), buff("opaline hardtear")]).is_ok());
+
+        let crab = buff("boiled crab");
+        let mut set = core::ArmorSet::new();
+        set.physical = 300;
+        set.fire = 200;
+        let buffed = set.with_buffs(&[crab]);
+        // 70% gets through the armor, and 90% of that through the crab.
+        assert_eq!(buffed.physical, 370);
+        assert_eq!(buffed.fire, 200);
+
+        let profile = core::DamageProfile::parse("mix", "physical=50 fire=50").unwrap();
+        let weights = profile.with_buffs(&[crab]).weights;
+        assert!((weights[0] - 45.0).abs() < 1e-4 && (weights[5] - 50.0).abs() < 1e-4);
+
+        let table = core::side_by_side(&set, &buffed, "without buffs:", "with buffs:");
+        assert!(table.starts_with("without buffs:"));
+        assert!(table
+            .lines()
+            .any(|x| x.starts_with("physical:   30") && x.ends_with("37")));
+    }
+
+    #[test]
+    fn test_character_resistances() {
+        let character = core::Character {
+            level: 150,
+            vigor: 60,
+            mind: 30,
+            endurance: 40,
+            strength: 1,
+            intelligence: 1,
+            arcane: 1,
+        };
+        assert_eq!(character.resistances(), [1650, 1450, 1350, 1050]);
+        assert_eq!(character.stat(16), 5500);
+
+        let total = core::Constraint::parse("robustness=400").unwrap();
+        assert_eq!(total, core::Constraint { stat: 9, min: 4000 });
+        assert_eq!(
+            character.armor_constraint(&total),
+            core::Constraint { stat: 9, min: 2550 }
+        );
+
+        let mut pieces = small_pieces();
+        pieces[0].robustness = 2550;
+        pieces[1].robustness = 2549;
+        for piece in &mut pieces {
+            piece.maximize_stat = piece.fire;
+        }
+        let constraints = [character.armor_constraint(&total)];
+        let result = core::get_set_constrained(WEIGHT_RESTRICTION, pieces, &constraints).unwrap();
+        assert_eq!(result.helm, "magic hood");
+        assert_eq!(result.with_character(&character).robustness, 4000);
+
+        // A Stalwart Horn adds 90 of the 400, leaving 165 to the armor next to the character.
+        let horn = talisman("stalwart horn");
+        let total = total.without_talismans(&[horn]);
+        assert_eq!(total, core::Constraint { stat: 9, min: 3100 });
+        assert_eq!(
+            character.armor_constraint(&total),
+            core::Constraint { stat: 9, min: 1650 }
+        );
+        let resistances = core::Constraint {
+            stat: 16,
+            min: 1000,
+        };
+        assert_eq!(resistances.without_talismans(&[horn]).min, 100);
+        let fire = core::Constraint { stat: 5, min: 100 };
+        assert_eq!(fire.without_talismans(&[horn]), fire);
+    }
+
+    #[test]
+    fn test_damage_taken() {
+        assert!((core::defense_multiplier(800.0, 100.0) - 0.9).abs() < 1e-6);
+        assert!((core::defense_multiplier(250.0, 100.0) - 0.7).abs() < 1e-6);
+        assert!((core::defense_multiplier(100.0, 100.0) - 0.4).abs() < 1e-6);
+        assert!((core::defense_multiplier(10.0, 100.0) - 0.1).abs() < 1e-6);
+
+        let character = core::Character {
+            level: 1,
+            vigor: 1,
+            mind: 1,
+            endurance: 1,
+            strength: 1,
+            intelligence: 1,
+            arcane: 1,
+        };
+        assert_eq!(character.defense(), [40.0; 8]);
+
+        // 400 magic is ten times the defense, so 90% gets past it, then the hood's 9% negation.
+        let attack = core::Attack::parse("magic=400").unwrap();
+        let result = core::get_set_survival(
+            WEIGHT_RESTRICTION,
+            small_pieces(),
+            &attack,
+            &character,
+            1000,
+            &[],
+        );
+        assert_eq!(result.helm, "magic hood");
+        assert!((attack.damage(&result, &character) - 327.6).abs() < 1e-3);
+        assert_eq!(attack.hits_to_die(&result, &character, 1000), 4);
+
+        let other = core::find_set(&small_pieces(), ["even helm", "", "", ""]).unwrap();
+        assert_eq!(attack.hits_to_die(&other, &character, 1000), 3);
+        assert!(core::find_set(&small_pieces(), ["no helm", "", "", ""]).is_err());
+
+        // Magic fortification already takes a share of the magic, so fire negation is worth more.
+        let attack = core::Attack::parse("magic=400 fire=500").unwrap();
+        let best = |buffs: &[usize]| {
+            core::get_set_survival(
+                WEIGHT_RESTRICTION,
+                small_pieces(),
+                &attack,
+                &character,
+                1000,
+                buffs,
+            )
+            .helm
+        };
+        let fortification = buff("magic fortification");
+        assert_eq!(best(&[]), "magic hood");
+        assert_eq!(best(&[fortification]), "even helm");
+    }
+
+    #[test]
+    fn test_stagger() {
+        assert_eq!(core::hits_before_stagger(500, 100), 4);
+        assert_eq!(core::hits_before_stagger(510, 100), 5);
+        assert_eq!(core::hits_before_stagger(0, 100), 0);
+
+        let bull_goat = talisman("bull-goat's talisman");
+        assert_eq!(core::stagger_constraint(3, 100, &[]).min, 301);
+        // Far more poise than any set has can't be met, rather than overflowing.
+        assert_eq!(core::stagger_constraint(5000, 1000, &[]).min, u16::MAX);
+        let constraint = core::stagger_constraint(3, 100, &[bull_goat]);
+        assert_eq!(constraint.min, 227);
+        let mut set = core::ArmorSet::new();
+        set.poise = constraint.min;
+        assert_eq!(
+            core::hits_before_stagger(set.with_talismans(&[bull_goat]).poise, 100),
+            3
+        );
+        set.poise -= 1;
+        assert_eq!(
+            core::hits_before_stagger(set.with_talismans(&[bull_goat]).poise, 100),
+            2
+        );
+
+        // The fire helm is the best without the constraint, the poise helm with it.
+        let mut pieces = small_pieces();
+        pieces.push(piece(
+            core::Slot::Helm,
+            "poise helm",
+            [0, 0, 0, 0, 0, 20, 0, 0, 0, 0, 0, 0, 310],
+            30,
+        ));
+        for piece in pieces.iter_mut() {
+            piece.maximize_stat = piece.fire;
+        }
+        let result = core::get_set_constrained(WEIGHT_RESTRICTION, pieces.clone(), &[]).unwrap();
+        assert_eq!(result.helm, "even helm");
+        let constraints = [core::stagger_constraint(3, 100, &[])];
+        let result = core::get_set_constrained(WEIGHT_RESTRICTION, pieces, &constraints).unwrap();
+        assert_eq!(result.helm, "poise helm");
+    }
+
+    #[test]
+    fn test_status_buildup() {
+        let bleed = core::Buildup::parse("bleed=55").unwrap();
+        assert_eq!(core::STATUSES[bleed.status].0, "hemorrhage");
+        assert_eq!(bleed.per_hit, 550);
+        assert_eq!(
+            core::Buildup::parse("scarlet rot = 40.5").unwrap().per_hit,
+            405
+        );
+        assert!(core::Buildup::parse("fire=55").is_err());
+        assert!(core::Buildup::parse("frost=0").is_err());
+
+        let mut set = core::ArmorSet::new();
+        set.robustness = 1650;
+        assert_eq!(bleed.hits_before_proc(&set), 2);
+        set.robustness = 1651;
+        assert_eq!(bleed.hits_before_proc(&set), 3);
+
+        let constraint = bleed.constraint(3);
+        assert_eq!(constraint.stat, 9);
+        assert_eq!(constraint.min, 1651);
+        assert_eq!(bleed.constraint(u32::MAX).min, u16::MAX);
+
+        // A worn Stalwart Horn covers 90 of it, so the armor needs only 75.1.
+        let horn = talisman("stalwart horn");
+        assert_eq!(constraint.without_talismans(&[horn]).min, 751);
+        set.robustness = 751;
+        assert_eq!(bleed.hits_before_proc(&set.with_talismans(&[horn])), 3);
+
+        // Only the robust helm takes a hit of 5 bleed buildup before the proc.
+        let mut pieces = small_pieces();
+        pieces.push(piece(
+            core::Slot::Helm,
+            "robust helm",
+            [0, 0, 0, 0, 0, 10, 0, 0, 0, 60, 0, 0, 0],
+            30,
+        ));
+        for piece in pieces.iter_mut() {
+            piece.maximize_stat = piece.fire;
+        }
+        let bleed = core::Buildup::parse("bleed=5").unwrap();
+        let constraints = [bleed.constraint(1)];
+        let result = core::get_set_constrained(WEIGHT_RESTRICTION, pieces, &constraints).unwrap();
+        assert_eq!(result.helm, "robust helm");
+        assert_eq!(bleed.hits_before_proc(&result), 1);
+    }
+
+    #[test]
+    fn test_endurance_planner() {
+        assert_eq!(
+            core::min_endurance(539, core::Roll::Medium, 300, &[]),
+            Some(60)
+        );
+        assert_eq!(core::min_endurance(0, core::Roll::Light, 0, &[]), Some(1));
+        assert_eq!(core::min_endurance(2000, core::Roll::Heavy, 0, &[]), None);
+
+        assert_eq!(core::level_cost(1), 673);
+        assert_eq!(core::levels_cost(1, 2), 673 + core::level_cost(2));
+        assert_eq!(core::levels_cost(1, 0), 0);
+        // Nothing past the highest level, and no overflow getting there.
+        assert_eq!(
+            core::levels_cost(core::MAX_LEVEL - 1, 3),
+            core::level_cost(712)
+        );
+        assert_eq!(core::levels_cost(65535, 3), 0);
+
+        // Taking 60 robustness needs the robust helm, 3.0 of armor on top of 30.0 of weapons.
+        let mut pieces = small_pieces();
+        pieces.push(piece(
+            core::Slot::Helm,
+            "robust helm",
+            [0, 0, 0, 0, 0, 10, 0, 0, 0, 600, 0, 0, 0],
+            30,
+        ));
+        let constraints = [core::Constraint { stat: 9, min: 600 }];
+        let (endurance, set) =
+            core::min_endurance_constrained(&pieces, &constraints, core::Roll::Medium, 300, &[])
+                .unwrap();
+        assert_eq!(set.helm, "robust helm");
+        assert_eq!(
+            Some(endurance),
+            core::min_endurance(30, core::Roll::Medium, 300, &[])
+        );
+    }
+
+    #[test]
+    fn test_cache() {
+        let cache_dir = TempCache::new("cache");
+        let page = wiki_page(20);
+        assert!(core::save_to_file(&core::Slot::Helm, &cache_dir, &page));
+        assert!(core::save_to_file(
+            &core::Slot::Chest,
+            &cache_dir,
+            &"oops".to_string()
+        ));
+
+        let entries = core::cache_entries(&cache_dir);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].slot, core::Slot::Helm);
+        assert_eq!(entries[0].size, page.len() as u64);
+
+        assert_eq!(core::verify_cache(&core::Slot::Helm, &cache_dir), Ok(20));
+        assert!(core::verify_cache(&core::Slot::Chest, &cache_dir).is_err());
+        assert!(core::verify_cache(&core::Slot::Legs, &cache_dir).is_err());
+
+        assert_eq!(core::clear_cache(&cache_dir), Ok(2));
+        assert!(core::cache_entries(&cache_dir).is_empty());
+    }
+
+    #[test]
+    fn test_validate_page() {
+        assert_eq!(
+            core::validate_page(&core::Slot::Helm, &wiki_page(20)),
+            Ok(20)
+        );
+        assert!(core::validate_page(&core::Slot::Helm, &wiki_page(3)).is_err());
+        let challenge =
+            "<html><title>Just a moment...</title><body>Checking your browser</body></html>";
+        assert!(core::validate_page(&core::Slot::Helm, challenge).is_err());
+
+        let fetcher = core::Fetcher::new(&core::HttpConfig::default()).unwrap();
+        let fetch = |(url, _)| {
+            let urls = std::collections::HashMap::from([("Helm".to_string(), url)]);
+            core::load_from_web(&core::Slot::Helm, &urls, &fetcher).map(|(text, _)| text)
+        };
+        let html = "Content-Type: text/html; charset=utf-8\r\n";
+        let page = wiki_page(20);
+        assert_eq!(fetch(serve("200 OK", html, page.clone())), Ok(page.clone()));
+        let error = fetch(serve("403 Forbidden", html, wiki_page(20))).unwrap_err();
+        assert!(error.contains("403"), "{error}");
+        // Media types are case-insensitive.
+        let upper = "Content-Type: Text/HTML; charset=UTF-8\r\n";
+        assert_eq!(fetch(serve("200 OK", upper, page.clone())), Ok(page));
+        let json = "Content-Type: application/json\r\n";
+        assert!(fetch(serve("200 OK", json, "{}".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_parsed_cache() {
+        assert_eq!(core::source_hash(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(core::source_hash("a"), 0xaf63_dc4c_8601_ec8c);
+
+        let cache_dir = TempCache::new("parsed");
+        let page = wiki_page(20);
+        let hash = core::source_hash(&page);
+        let slot = core::Slot::Helm;
+
+        let parsed = core::get_slot_pieces(&slot, &page, &cache_dir, 5).unwrap();
+        assert_eq!(parsed, core::get_pieces_from_text(&slot, &page, 5));
+        assert_eq!(
+            core::load_parsed(&slot, &cache_dir, hash, 5),
+            Some(parsed.clone())
+        );
+        assert!(core::load_parsed(&slot, &cache_dir, hash ^ 1, 5).is_none());
+
+        // The cached pieces are used while the page is the same, whatever the page says.
+        let mut changed = parsed.clone();
+        changed[0].poise = 999;
+        assert!(core::save_parsed(&slot, &cache_dir, hash, &changed));
+        assert_eq!(
+            core::get_slot_pieces(&slot, &page, &cache_dir, 5),
+            Ok(changed)
+        );
+
+        // A different page, or a different parser version, is parsed again.
+        let other = wiki_page(21);
+        assert_eq!(
+            core::get_slot_pieces(&slot, &other, &cache_dir, 5)
+                .unwrap()
+                .len(),
+            21
+        );
+        let file = core::parsed_file(&slot, &cache_dir);
+        let text = std::fs::read_to_string(&file).unwrap();
+        let (_, rest) = text.split_once('\n').unwrap();
+        let old_version = format!("elden_boc-pieces 0 {hash:016x}\n{rest}");
+        std::fs::write(&file, old_version).unwrap();
+        assert!(core::load_parsed(&slot, &cache_dir, hash, 5).is_none());
+
+        // A page without armor rows has no pieces, and isn't cached as if it had.
+        let empty = armor_table("Helm", 0);
+        assert!(core::get_pieces_from_text(&slot, &empty, 5).is_empty());
+        assert!(core::get_slot_pieces(&slot, &empty, &cache_dir, 5).is_err());
+    }
+
+    #[test]
+    fn test_conditional_refresh() {
+        let cache_dir = TempCache::new("refresh");
+        let slot = core::Slot::Helm;
+        let fetcher = core::Fetcher::new(&core::HttpConfig::default()).unwrap();
+        let refresh = |(url, handle): (String, std::thread::JoinHandle<Vec<String>>)| {
+            let urls = std::collections::HashMap::from([("Helm".to_string(), url)]);
+            let changed = core::refresh_cache(&slot, &cache_dir, &urls, &fetcher, true);
+            (changed, handle.join().unwrap().concat())
+        };
+        let html = "Content-Type: text/html\r\n";
+        let validators = "Content-Type: text/html\r\nETag: \"v1\"\r\n\
+                          Last-Modified: Wed, 21 Oct 2015 07:28:00 GMT\r\n";
+
+        // Nothing is cached, so the first fetch is unconditional.
+        let (changed, request) = refresh(serve("200 OK", validators, wiki_page(20)));
+        assert_eq!(changed, Ok(true));
+        assert!(!request.contains("if-none-match"));
+        assert_eq!(
+            core::load_validators(&slot, &cache_dir),
+            core::Validators {
+                etag: Some("\"v1\"".to_string()),
+                last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string()),
+            }
+        );
+
+        let (changed, request) = refresh(serve("304 Not Modified", "", String::new()));
+        assert_eq!(changed, Ok(false));
+        assert!(request.contains("if-none-match: \"v1\""), "{request}");
+        assert!(request.contains("if-modified-since: wed, 21 oct 2015 07:28:00 gmt"));
+
+        // A server that ignores the validators and sends the same page changes nothing.
+        let (changed, _) = refresh(serve("200 OK", html, wiki_page(20)));
+        assert_eq!(changed, Ok(false));
+        assert_eq!(
+            core::load_validators(&slot, &cache_dir),
+            core::Validators::default()
+        );
+
+        let (changed, _) = refresh(serve("200 OK", html, wiki_page(21)));
+        assert_eq!(changed, Ok(true));
+        assert_eq!(core::verify_cache(&slot, &cache_dir), Ok(21));
+
+        // Several slots are refreshed at once, each with its own outcome.
+        let (helm_url, helm) = serve("200 OK", html, wiki_page(21));
+        let (chest_url, chest) = serve("200 OK", html, wiki_page(20));
+        let urls = std::collections::HashMap::from([
+            ("Helm".to_string(), helm_url),
+            ("Chest".to_string(), chest_url),
+            ("Gauntlets".to_string(), "not a url".to_string()),
+        ]);
+        let slots = [core::Slot::Helm, core::Slot::Chest, core::Slot::Gauntlets];
+        let results = core::refresh_caches(&slots, &cache_dir, &urls, &fetcher, false);
+        helm.join().unwrap();
+        chest.join().unwrap();
+        assert_eq!(results[0], (core::Slot::Helm, Ok(false)));
+        assert_eq!(results[1], (core::Slot::Chest, Ok(true)));
+        assert_eq!(results[2].0, core::Slot::Gauntlets);
+        assert!(results[2].1.is_err());
+
+        assert_eq!(core::clear_cache(&cache_dir), Ok(2));
+    }
+
+    #[test]
+    fn test_http_config() {
+        let config = core::parse_config(
+            "# fetching\ntimeout = 5\nRetries= 1\n\nuser_agent = tester/1.0\nproxy =\n",
+        )
+        .unwrap();
+        assert_eq!(config.get("retries").map(String::as_str), Some("1"));
+        let http = core::HttpConfig::from_config(&config).unwrap();
+        assert_eq!(http.timeout, std::time::Duration::from_secs(5));
+        assert_eq!(http.retries, 1);
+        assert_eq!(http.user_agent, "tester/1.0");
+        assert_eq!(http.proxy, None);
+        assert_eq!(http.backoff, core::HttpConfig::default().backoff);
+
+        assert!(core::parse_config("timeout 5").is_err());
+        let config = core::parse_config("retries = many").unwrap();
+        assert!(core::HttpConfig::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_fetch_retries() {
+        let http = core::HttpConfig {
+            retries: 2,
+            backoff: std::time::Duration::from_millis(1),
+            ..core::HttpConfig::default()
+        };
+        let fetcher = core::Fetcher::new(&http).unwrap();
+        let html = "Content-Type: text/html\r\n";
+        let page = wiki_page(20);
+        let fetch = |(url, handle): (String, std::thread::JoinHandle<Vec<String>>)| {
+            let urls = std::collections::HashMap::from([("Helm".to_string(), url)]);
+            let text = core::load_from_web(&core::Slot::Helm, &urls, &fetcher).map(|x| x.0);
+            (text, handle.join().unwrap())
+        };
+
+        // Server errors are retried, and the page still arrives.
+        let (text, requests) = fetch(serve_all(vec![
+            response("503 Service Unavailable", "", ""),
+            response("500 Internal Server Error", "", ""),
+            response("200 OK", html, &page),
+        ]));
+        assert_eq!(text, Ok(page.clone()));
+        assert_eq!(requests.len(), 3);
+        assert!(
+            requests[0].contains("user-agent: elden_boc/"),
+            "{}",
+            requests[0]
+        );
+
+        // Until the retries run out.
+        let (text, requests) = fetch(serve_all(vec![
+            response("503 Service Unavailable", "", "");
+            3
+        ]));
+        assert!(text.unwrap_err().contains("503"));
+        assert_eq!(requests.len(), 3);
+
+        // Client errors aren't retried.
+        let (text, requests) = fetch(serve("404 Not Found", html, String::new()));
+        assert!(text.is_err());
+        assert_eq!(requests.len(), 1);
+
+        // All four slots are fetched and cached together.
+        let cache_dir = TempCache::new("fetch");
+        let mut urls = std::collections::HashMap::new();
+        let mut handles = Vec::new();
+        for slot in &core::SLOTS {
+            let (url, handle) = serve("200 OK", html, page.clone());
+            urls.insert(slot.to_string(), url);
+            handles.push(handle);
+        }
+        let pieces = core::try_get_pieces_in(&cache_dir, 0, &urls, &fetcher).unwrap();
+        assert_eq!(pieces.len(), 4 * 21);
+        for slot in &core::SLOTS {
+            assert_eq!(pieces.iter().filter(|x| &x.slot == slot).count(), 21);
+        }
+        for handle in handles {
+            assert_eq!(handle.join().unwrap().len(), 1);
+        }
+        assert_eq!(core::clear_cache(&cache_dir), Ok(4));
+    }
+
+    #[test]
+    fn test_page_urls() {
+        let urls = core::urls_from_config(&std::collections::HashMap::new()).unwrap();
+        assert_eq!(urls, core::wiki_urls());
+        assert_eq!(
+            urls["Legs"],
+            "https://eldenring.wiki.fextralife.com/Leg+Armor"
+        );
+
+        let config = core::parse_config(
+            "base_url = http://mirror.local/wiki/\nhelm_url = file-server.local/helms.html",
+        )
+        .unwrap();
+        let urls = core::urls_from_config(&config).unwrap();
+        assert_eq!(urls["Helm"], "file-server.local/helms.html");
+        assert_eq!(urls["Chest"], "http://mirror.local/wiki/Chest+Armor");
+        assert_eq!(urls.len(), 4);
+
+        let config = core::parse_config("boots_url = http://mirror.local/boots").unwrap();
+        assert!(core::urls_from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_page_sections() {
+        let page = format!(
+            "<html><body><h2>Base Game Helms</h2>{}\
+             <h3>Notes</h3><table><tbody><tr><td>a</td><td>b</td></tr></tbody></table>\
+             <h2>Shadow of the   Erdtree\nHelms</h2>{}</body></html>",
+            armor_table("Helm", 20),
+            armor_table("DLC Helm", 2),
+        );
+        let pieces = core::try_pieces_from_text(&core::Slot::Helm, &page, 0).unwrap();
+        assert_eq!(pieces.len(), 22);
+        assert!(pieces[..20].iter().all(|x| x.section == "Base Game Helms"));
+        assert_eq!(pieces[20].name, "dlc helm 0");
+        assert_eq!(pieces[21].section, "Shadow of the Erdtree Helms");
+
+        let notes = "<html><body><table><tbody><tr><td>a</td></tr></tbody></table></body></html>";
+        assert!(core::try_pieces_from_text(&core::Slot::Helm, notes, 0).is_err());
+
+        // The sections survive the parsed cache.
+        let cache_dir = TempCache::new("sections");
+        let hash = core::source_hash(&page);
+        assert!(core::save_parsed(
+            &core::Slot::Helm,
+            &cache_dir,
+            hash,
+            &pieces
+        ));
+        assert_eq!(
+            core::load_parsed(&core::Slot::Helm, &cache_dir, hash, 0),
+            Some(pieces)
+        );
+    }
+
+    #[test]
+    fn test_dlc() {
+        assert!(core::is_dlc("helm 0", "Shadow of the Erdtree Helms"));
+        assert!(core::is_dlc("helm 0", "DLC Armor"));
+        assert!(core::is_dlc("black knight helm", "Helms"));
+        for name in [
+            "leda's armor",
+            "moore's armor",
+            "dancer's hood",
+            "greatjar",
+            "divine beast head",
+            "crucible hammer-helm",
+        ] {
+            assert!(core::is_dlc(name, "Armor"), "{name}");
+        }
+        assert!(!core::is_dlc("cleanrot helm", "Helms"));
+        assert!(!core::is_dlc("helm 0", "Dlcx Notes"));
+
+        let page = format!(
+            "<html><body><h2>Helms</h2>{}<h2>Shadow of the Erdtree Helms</h2>{}</body></html>",
+            armor_table("Helm", 20),
+            armor_table("DLC Helm", 2),
+        );
+        let mut pieces = core::try_pieces_from_text(&core::Slot::Helm, &page, 0).unwrap();
+        assert_eq!(pieces.iter().filter(|x| x.dlc).count(), 2);
+        core::filter_dlc(&mut pieces, true);
+        assert_eq!(pieces.len(), 22);
+        core::filter_dlc(&mut pieces, false);
+        assert_eq!(pieces.len(), 20);
+        assert!(pieces.iter().all(|x| !x.dlc));
+
+        // A single table mixing both, tagged by name.
+        let table = armor_table("Helm", 20)
+            .replace("<td>Helm 0</td>", "<td>Rakshasa Helm</td>")
+            .replace("<td>Helm 1</td>", "<td>Cleanrot Helm</td>")
+            .replace("<td>Helm 2</td>", "<td>Circlet of Light</td>");
+        let page = format!("<html><body><h2>Helms</h2>{table}</body></html>");
+        let pieces = core::try_pieces_from_text(&core::Slot::Helm, &page, 0).unwrap();
+        let dlc: Vec<&str> = pieces
+            .iter()
+            .filter(|x| x.dlc)
+            .map(|x| x.name.as_str())
+            .collect();
+        assert_eq!(dlc, ["rakshasa helm", "circlet of light"]);
+
+        let config = |text| core::dlc_from_config(&core::parse_config(text).unwrap());
+        assert_eq!(config(""), Ok(None));
+        assert_eq!(config("dlc = No"), Ok(Some(false)));
+        assert_eq!(config("dlc = yes"), Ok(Some(true)));
+        assert!(config("dlc = maybe").is_err());
+    }
+
+    #[test]
+    fn test_unobtainable() {
+        let list = core::unobtainable();
+        assert!(list.iter().all(|x| !x.reason.is_empty()));
+
+        let list = core::parse_unobtainable(
+            "# comment\nversion 1\nhelm | Ragged Hat | only worn by NPCs\n",
+        )
+        .unwrap();
+        assert_eq!(
+            list,
+            vec![core::Unobtainable {
+                slot: core::Slot::Helm,
+                name: "ragged hat".to_string(),
+                reason: "only worn by NPCs".to_string(),
+            }]
+        );
+        assert!(core::parse_unobtainable("helm | ragged hat | npc").is_err());
+        assert!(core::parse_unobtainable("version 2\nhelm | ragged hat | npc").is_err());
+        assert!(core::parse_unobtainable("version 1\nboots | ragged hat | npc").is_err());
+        assert!(core::parse_unobtainable("version 1\nhelm | ragged hat").is_err());
+
+        // Only the exact piece goes, not others whose names contain it or the same name elsewhere.
+        let mut pieces = vec![
+            piece(core::Slot::Helm, "ragged hat", [0; 13], 10),
+            piece(core::Slot::Helm, "ragged hat of the tarnished", [0; 13], 10),
+            piece(core::Slot::Chest, "ragged hat", [0; 13], 10),
+        ];
+        let (removed, stale) = core::remove_unobtainable(&mut pieces, &list);
+        assert_eq!(removed, vec![&list[0]]);
+        assert!(stale.is_empty());
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(pieces[0].name, "ragged hat of the tarnished");
+        assert_eq!(pieces[1].slot, core::Slot::Chest);
+        let (removed, stale) = core::remove_unobtainable(&mut pieces, &list);
+        assert!(removed.is_empty());
+        assert_eq!(stale, vec![&list[0]]);
+
+        // Every piece the old name keywords left out is on the list.
+        let listed = core::unobtainable();
+        for name in [
+            "brave's battlewear (altered)",
+            "ragged hat (altered)",
+            "ragged armor (altered)",
+            "millicent's gloves",
+            "golden prosthetic",
+        ] {
+            assert!(listed.iter().any(|x| x.name == name), "{name}");
+        }
+
+        // The report counts what was left out, and warns about entries that match nothing.
+        let mut pieces = vec![
+            piece(core::Slot::Helm, "ragged hat", [0; 13], 10),
+            piece(core::Slot::Helm, "black knight helm", [0; 13], 10),
+        ];
+        pieces[1].dlc = true;
+        let adjustments = core::adjust_pieces(&mut pieces, &[], 0, false, false);
+        assert!(pieces.is_empty());
+        assert_eq!(adjustments.removed.len(), 1);
+        assert_eq!(adjustments.stale.len(), listed.len() - 1);
+        let messages = adjustments.messages();
+        assert_eq!(messages[0], "pieces left out as unobtainable: 1");
+        assert!(messages[1].starts_with("warning: unobtainable"));
+        assert!(core::adjust_pieces(&mut pieces, &[], 0, true, true)
+            .messages()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_overrides() {
+        let overrides = core::parse_overrides(
+            "# fixes\n\
+             patch | helm | Cleanrot Helm | poise=9 weight=6.4\n\
+             add | chest | new armor | physical=5.1\n\
+             remove | legs | bogus greaves\n\
+             patch | gauntlets | gone gauntlets | poise=1\n\
+             add | helm | cleanrot helm | poise=1\n",
+        )
+        .unwrap();
+        assert_eq!(overrides.len(), 5);
+        assert_eq!(overrides[0].line, 2);
+        assert_eq!(overrides[0].name, "cleanrot helm");
+        assert_eq!(overrides[0].stats[12], Some(90));
+        assert_eq!(overrides[0].weight, Some(64));
+        assert_eq!(overrides[2].kind, core::OverrideKind::Remove);
+        assert!(core::parse_overrides("patch | helm | cleanrot helm").is_err());
+        assert!(core::parse_overrides("patch | helm | cleanrot helm | ").is_err());
+        assert!(core::parse_overrides("patch | boots | cleanrot helm | poise=1").is_err());
+        assert!(core::parse_overrides("patch | helm | cleanrot helm | luck=1").is_err());
+        assert!(core::parse_overrides("patch | helm | cleanrot helm | poise=x").is_err());
+        assert!(core::parse_overrides("rename | helm | cleanrot helm").is_err());
+        for value in ["-1", "NaN", "inf", "200.1", "6553.5"] {
+            let line = format!("patch | helm | cleanrot helm | poise={value}");
+            let e = core::parse_overrides(&line).unwrap_err();
+            assert!(e.starts_with("line 1: poise="), "{e}");
+        }
+        let negations: Vec<String> = core::STATS[..8]
+            .iter()
+            .map(|x| format!("{x}=200"))
+            .collect();
+        let line = format!("add | helm | tower | {} weight=200", negations.join(" "));
+        let most = core::parse_overrides(&line).unwrap();
+        assert_eq!(most[0].stats[0], Some(2000));
+        // Four pieces at the most add up without overflowing, averages included.
+        let mut pieces = small_pieces();
+        core::apply_overrides(&mut pieces, &most, 15);
+        let tower = pieces.iter().find(|x| x.name == "tower").unwrap().clone();
+        let set = core::ArmorSet::from(tower.clone(), tower.clone(), tower.clone(), tower);
+        assert_eq!(set.stat(15), 8000);
+        assert_eq!(set.maximize_stat, 64000);
+        let upper = core::parse_overrides(
+            "PATCH | Helm | cleanrot helm | poise=1
+Remove | legs | x",
+        )
+        .unwrap();
+        assert_eq!(upper[0].kind, core::OverrideKind::Patch);
+        assert_eq!(upper[1].kind, core::OverrideKind::Remove);
+
+        let mut pieces = vec![
+            piece(core::Slot::Helm, "cleanrot helm", [30; 13], 50),
+            piece(core::Slot::Legs, "bogus greaves", [0; 13], 10),
+        ];
+        let (applied, unmatched) = core::apply_overrides(&mut pieces, &overrides, 12);
+        assert_eq!(applied, vec![&overrides[0], &overrides[1], &overrides[2]]);
+        assert_eq!(unmatched, vec![&overrides[3], &overrides[4]]);
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(pieces[0].poise, 90);
+        assert_eq!(pieces[0].physical, 30);
+        assert_eq!(pieces[0].weight, 64);
+        assert_eq!(pieces[0].maximize_stat, 90);
+        assert_eq!(pieces[1].slot, core::Slot::Chest);
+        assert_eq!(pieces[1].physical, 51);
+        assert_eq!(pieces[1].poise, 0);
+        assert!(unmatched[0].warning().contains("matches no piece"));
+        assert!(unmatched[1].warning().contains("already listed"));
+        assert!(!pieces[1].dlc);
+
+        // Added pieces are tagged like parsed ones, and the report lists every override.
+        let overrides = core::parse_overrides(
+            "add | helm | rakshasa helm | poise=1
+remove | legs | bogus greaves
+",
+        )
+        .unwrap();
+        let mut pieces: Vec<core::ArmorPiece> = Vec::new();
+        let adjustments = core::adjust_pieces(&mut pieces, &overrides, 12, true, true);
+        assert!(pieces[0].dlc);
+        assert_eq!(
+            adjustments.messages(),
+            vec![
+                "applied override line 1: add Helm rakshasa helm",
+                "warning: override line 2: remove Legs bogus greaves matches no piece",
+            ]
+        );
+        core::adjust_pieces(&mut pieces, &[], 12, false, true);
+        assert!(pieces.is_empty());
+    }
 }
@@ -1,95 +1,1057 @@
+use std::collections::HashMap;
 use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 pub mod core;
 
-fn main() {
-    let keys: std::vec::Vec<String> = vec![
-        "physical".to_string(),
-        "strike".to_string(),
-        "slash".to_string(),
-        "pierce".to_string(),
-        "magic".to_string(),
-        "fire".to_string(),
-        "lightning".to_string(),
-        "holy".to_string(),
-        "immunity".to_string(),
-        "robustness".to_string(),
-        "focus".to_string(),
-        "vitality".to_string(),
-        "poise".to_string(),
+const USAGE: &str = "usage: elden_boc [--cache-dir DIR] [--base-url URL] [--url SLOT=URL]... \
+                     [--dlc | --no-dlc] [--include-unobtainable] [cache list | cache refresh [--if-changed] [SLOT] | cache clear | cache verify]";
+
+/// Print `message` and return the trimmed line the user typed.
+fn prompt(message: &str) -> String {
+    let mut input = String::new();
+    print!("{message}");
+    std::io::stdout().flush().expect("Failed to flush stdout");
+    std::io::stdin()
+        .read_line(&mut input)
+        .expect("Failed to get user input.");
+    input.trim().to_string()
+}
+
+/// Ask for a list of indexes into `options`, separated by spaces or commas.
+fn prompt_indexes(message: &str, options: &[&str], allow_empty: bool) -> Vec<usize> {
+    loop {
+        for (index, option) in options.iter().enumerate() {
+            println!("{index}: {option:#?}");
+        }
+        let input = prompt(message);
+        let indexes: Result<Vec<usize>, _> = input
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|x| !x.is_empty())
+            .map(str::parse)
+            .collect();
+        match indexes {
+            Ok(indexes)
+                if (allow_empty || !indexes.is_empty())
+                    && indexes.iter().all(|&x| x < options.len()) =>
+            {
+                return indexes;
+            }
+            _ => println!("{input} is not a list of available options"),
+        }
+    }
+}
+
+/// The kinds of set the user can look for.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Mode {
+    Stat,
+    Maximin,
+    Ratio,
+    NearOptimal,
+    DamageMix,
+    Boss,
+    Talismans,
+    Survival,
+    Endurance,
+}
+
+impl Mode {
+    /// Every mode, in the order they're listed.
+    const ALL: [Self; 9] = [
+        Self::Stat,
+        Self::Maximin,
+        Self::Ratio,
+        Self::NearOptimal,
+        Self::DamageMix,
+        Self::Boss,
+        Self::Talismans,
+        Self::Survival,
+        Self::Endurance,
     ];
 
+    const fn description(self) -> &'static str {
+        match self {
+            Self::Stat => "maximize one stat",
+            Self::Maximin => "maximize the lowest of a group of stats",
+            Self::Ratio => "maximize a stat per unit of weight",
+            Self::NearOptimal => "lightest set within a percentage of the best stat",
+            Self::DamageMix => "least damage taken from a damage mix",
+            Self::Boss => "least damage taken from a boss",
+            Self::Talismans => "maximize one stat with armor and talismans",
+            Self::Survival => "most hits survived from an attack",
+            Self::Endurance => "lowest Endurance level for a set",
+        }
+    }
+
+    /// Whether the mode maximizes a single stat the user picks.
+    const fn maximizes_stat(self) -> bool {
+        matches!(
+            self,
+            Self::Stat | Self::Ratio | Self::NearOptimal | Self::Talismans
+        )
+    }
+
+    /// Whether the mode can take lower bounds on the set's stats.
+    const fn constrained(self) -> bool {
+        matches!(
+            self,
+            Self::Stat | Self::DamageMix | Self::Boss | Self::Endurance
+        )
+    }
+}
+
+/// What the result is shown with on top of the armor alone.
+struct Extras<'a> {
+    buffs: &'a [usize],
+    character: Option<&'a core::Character>,
+    /// Talismans worn on top of the set, indexes into `core::TALISMANS`.
+    talismans: &'a [usize],
+    /// Poise damage per hit of the enemy attack in tenths, 0 to leave out the stagger line.
+    poise_damage: u16,
+    buildups: &'a [core::Buildup],
+}
+
+/// Print the set, next to how it looks in game with the buffs, worn talismans and the
+/// character's own resistances on top, if there are any.
+fn print_set(result: &core::ArmorSet, extras: &Extras) {
+    let mut in_game = result.with_buffs(extras.buffs);
+    let mut titles: Vec<&str> = Vec::new();
+    if !extras.buffs.is_empty() {
+        titles.push("buffs");
+    }
+    if !extras.talismans.is_empty() {
+        in_game = in_game.with_talismans(extras.talismans);
+        titles.push("talismans");
+    }
+    if let Some(character) = extras.character {
+        in_game = in_game.with_character(character);
+        titles.push("character");
+    }
+    if titles.is_empty() {
+        println!("\n{}", result);
+    } else {
+        let title = format!("with {}:", titles.join(" and "));
+        println!(
+            "\n{}",
+            core::side_by_side(result, &in_game, "armor alone:", &title)
+        );
+    }
+    if extras.poise_damage > 0 {
+        let poise = result.with_talismans(extras.talismans).poise;
+        println!(
+            "poise: {}, hits taken before stagger: {}",
+            f32::from(poise) / 10.0,
+            core::hits_before_stagger(poise, extras.poise_damage)
+        );
+    }
+    if !extras.buildups.is_empty() {
+        let mut worn = result.with_talismans(extras.talismans);
+        if let Some(character) = extras.character {
+            worn = worn.with_character(character);
+        }
+        for buildup in extras.buildups {
+            let (status, stat) = core::STATUSES[buildup.status];
+            println!(
+                "{status}: {} {} against {} per hit, hits taken before it procs: {}",
+                core::STATS[stat],
+                f32::from(worn.stat(stat)) / 10.0,
+                f32::from(buildup.per_hit) / 10.0,
+                buildup.hits_before_proc(&worn)
+            );
+        }
+    }
+}
+
+/// Ask until the user types something that parses into a `T`.
+fn prompt_parse<T: std::str::FromStr>(message: &str) -> T {
+    loop {
+        let input = prompt(message);
+        if let Ok(value) = input.parse() {
+            return value;
+        }
+        println!("Couldn't parse {input}. Try again!");
+    }
+}
+
+/// Ask for a weight and return it in tenths, like piece weights.
+fn prompt_weight(message: &str) -> u16 {
+    loop {
+        let input = prompt(message);
+        if let Ok(num) = input.parse::<f32>() {
+            return (num * 10.0).round() as u16;
+        }
+        println!("Couldn't parse input into a number. Try again!");
+    }
+}
+
+/// Remove `flag` and the value after it from `args`, returning the value.
+fn take_flag(args: &mut Vec<String>, flag: &str) -> Option<String> {
+    let index = args.iter().position(|x| x == flag)?;
+    if index + 1 >= args.len() {
+        println!("{flag} needs a value\n{USAGE}");
+        std::process::exit(1);
+    }
+    let value = args.remove(index + 1);
+    args.remove(index);
+    Some(value)
+}
+
+/// Remove `flag` from `args`, returning whether it was there.
+fn take_switch(args: &mut Vec<String>, flag: &str) -> bool {
+    let Some(index) = args.iter().position(|x| x == flag) else {
+        return false;
+    };
+    args.remove(index);
+    true
+}
+
+/// How long ago `time` was, in the largest whole unit.
+fn age(time: SystemTime) -> String {
+    let seconds = SystemTime::now()
+        .duration_since(time)
+        .map_or(0, |x| x.as_secs());
+    for (unit, length) in [("day", 86400), ("hour", 3600), ("minute", 60)] {
+        if seconds >= length {
+            let count = seconds / length;
+            let plural = if count == 1 { "" } else { "s" };
+            return format!("{count} {unit}{plural} ago");
+        }
+    }
+    "just now".to_string()
+}
+
+/// Run a cache command like `cache list` or `cache refresh helm` on `cache_dir`.
+fn cache_command(
+    args: &[String],
+    cache_dir: &Path,
+    urls: &HashMap<String, String>,
+    fetcher: &core::Fetcher,
+) -> Result<(), String> {
+    let [cache, command, rest @ ..] = args else {
+        return Err(USAGE.to_string());
+    };
+    if cache != "cache" {
+        return Err(USAGE.to_string());
+    }
+    match (command.as_str(), rest) {
+        ("list", []) => {
+            println!("cache directory: {}", cache_dir.display());
+            for entry in core::cache_entries(cache_dir) {
+                println!(
+                    "{}: {} bytes, fetched {}",
+                    entry.slot,
+                    entry.size,
+                    age(entry.fetched)
+                );
+            }
+        }
+        ("refresh", _) => {
+            // The flag can come before or after the slot.
+            let conditional = rest.iter().any(|x| x == "--if-changed");
+            let rest: Vec<&String> = rest.iter().filter(|x| *x != "--if-changed").collect();
+            if rest.len() > 1 {
+                return Err(USAGE.to_string());
+            }
+            let slots: Vec<core::Slot> = match rest.first() {
+                Some(name) => {
+                    let Some(slot) = core::slot_named(name) else {
+                        return Err(format!("{name} is not a slot"));
+                    };
+                    vec![slot]
+                }
+                None => core::SLOTS.to_vec(),
+            };
+            for slot in &slots {
+                println!("Fetching {slot} data from web");
+            }
+            let mut changed: Vec<String> = Vec::new();
+            let mut failed = false;
+            for (slot, result) in
+                core::refresh_caches(&slots, cache_dir, urls, fetcher, conditional)
+            {
+                match result {
+                    Ok(true) => {
+                        println!("successfully cached {slot}.html");
+                        changed.push(slot.to_string());
+                    }
+                    Ok(false) => println!("{slot}.html is up to date"),
+                    Err(e) => {
+                        println!("{e}");
+                        failed = true;
+                    }
+                }
+            }
+            if changed.is_empty() {
+                println!("no slot changed");
+            } else {
+                println!("changed: {}", changed.join(", "));
+            }
+            if failed {
+                return Err("some pages couldn't be refreshed, try again later".to_string());
+            }
+        }
+        ("clear", []) => {
+            let removed = core::clear_cache(cache_dir)?;
+            println!(
+                "removed {removed} cached pages from {}",
+                cache_dir.display()
+            );
+        }
+        ("verify", []) => {
+            let mut failed = false;
+            for slot in &core::SLOTS {
+                match core::verify_cache(slot, cache_dir) {
+                    Ok(count) => println!("{slot}: {count} pieces"),
+                    Err(e) => {
+                        println!("{slot}: {e}");
+                        failed = true;
+                    }
+                }
+            }
+            if failed {
+                return Err("some cached pages don't parse, refresh them".to_string());
+            }
+        }
+        _ => return Err(USAGE.to_string()),
+    }
+    Ok(())
+}
+
+fn main() {
+    // Get the settings, with flags over the config file, and run a cache command instead if one
+    // was given.
+    let exit = |e: String| -> ! {
+        println!("{e}");
+        std::process::exit(1);
+    };
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let mut config = core::load_config().unwrap_or_else(|e| exit(e));
+    let cache_override = take_flag(&mut args, "--cache-dir").map(PathBuf::from);
+    if let Some(base_url) = take_flag(&mut args, "--base-url") {
+        config.insert("base_url".to_string(), base_url);
+    }
+    while let Some(page) = take_flag(&mut args, "--url") {
+        let Some((slot, url)) = page.split_once('=') else {
+            exit(format!("expected --url SLOT=URL, got {page}"));
+        };
+        config.insert(
+            format!("{}_url", slot.trim().to_lowercase()),
+            url.trim().to_string(),
+        );
+    }
+    if take_switch(&mut args, "--dlc") {
+        config.insert("dlc".to_string(), "yes".to_string());
+    }
+    if take_switch(&mut args, "--no-dlc") {
+        config.insert("dlc".to_string(), "no".to_string());
+    }
+    if take_switch(&mut args, "--include-unobtainable") {
+        config.insert("include_unobtainable".to_string(), "yes".to_string());
+    }
+    let cache_dir = core::cache_dir(cache_override.as_deref()).unwrap_or_else(|e| exit(e));
+    let include_dlc = core::dlc_from_config(&config).unwrap_or_else(|e| exit(e));
+    let include_unobtainable = core::switch_from_config(&config, "include_unobtainable")
+        .unwrap_or_else(|e| exit(e))
+        .unwrap_or(false);
+    let urls = core::urls_from_config(&config).unwrap_or_else(|e| exit(e));
+    let fetcher = core::HttpConfig::from_config(&config)
+        .and_then(|http| core::Fetcher::new(&http))
+        .unwrap_or_else(|e| exit(e));
+    if !args.is_empty() {
+        if let Err(e) = cache_command(&args, &cache_dir, &urls, &fetcher) {
+            exit(e);
+        }
+        return;
+    }
+
+    // Get the kind of set the user is looking for.
+    let mode: Mode;
+    loop {
+        for (index, mode) in Mode::ALL.iter().enumerate() {
+            println!("{index}: {}", mode.description());
+        }
+        let input = prompt("Choose index of optimization mode: ");
+        mode = match input.parse::<usize>().ok().and_then(|x| Mode::ALL.get(x)) {
+            Some(mode) => *mode,
+            _ => {
+                println!("{input} is not an available mode");
+                continue;
+            }
+        };
+        break;
+    }
+
+    let constrained = mode.constrained();
+
     // Get the name of the stat the user wants to optimize.
-    let maximize_stat: usize;
+    let mut maximize_stat: usize = 0;
+    let mut group: Vec<usize> = Vec::new();
+    if mode == Mode::Maximin {
+        group = prompt_indexes(
+            "Choose indexes of stats to keep high, e.g. 4 5 6 7: ",
+            &core::STATS,
+            false,
+        );
+    } else if mode.maximizes_stat() {
+        loop {
+            for (index, key) in core::STATS.iter().enumerate() {
+                println!("{index}: {key:#?}");
+            }
+            let input = prompt("Choose index of stat to maximize: ");
+            maximize_stat = match input.parse() {
+                Ok(num) if num < core::STATS.len() => num,
+                _ => {
+                    println!("{input} is not an available stat");
+                    continue;
+                }
+            };
+            break;
+        }
+    }
+
+    // Get the lowest total of the stat worth carrying the weight for.
+    let mut min_stat: u16 = 0;
+    if mode == Mode::Ratio {
+        loop {
+            let input = prompt("Choose minimum total of the stat ([enter] for none): ");
+            if input.is_empty() {
+                break;
+            }
+            min_stat = if let Ok(num) = input.parse::<f32>() {
+                // Averages are compared as the total they're an average of.
+                ((num * 10.0) as u16).saturating_mul(core::stat_divisor(maximize_stat))
+            } else {
+                println!("Couldn't parse input into a number. Try again!");
+                continue;
+            };
+            break;
+        }
+    }
+
+    // Get how much of the best stat the user is willing to give up.
+    let mut percent: u32 = 100;
+    if mode == Mode::NearOptimal {
+        loop {
+            let input = prompt("Choose percentage of the best stat to keep, e.g. 98: ");
+            percent = match input.parse::<u32>() {
+                Ok(num) if num <= 100 => num,
+                _ => {
+                    println!("Couldn't parse input into a percentage. Try again!");
+                    continue;
+                }
+            };
+            break;
+        }
+    }
+
+    // Get the mix of damage the user expects to take.
+    let mut profile = core::DamageProfile {
+        name: String::new(),
+        weights: [0.0; 8],
+    };
+    if mode == Mode::DamageMix {
+        let mut profiles = core::preset_profiles();
+        match core::load_profiles() {
+            Ok(user_profiles) => profiles.extend(user_profiles),
+            Err(e) => println!("could not load damage profiles: {e}"),
+        }
+        loop {
+            for (index, profile) in profiles.iter().enumerate() {
+                println!("{index}: {profile}");
+            }
+            let input = prompt("Choose index of damage profile, or a mix like slash=60 holy=30: ");
+            if let Some(chosen) = input.parse::<usize>().ok().and_then(|x| profiles.get(x)) {
+                profile = chosen.clone();
+                break;
+            }
+            match core::DamageProfile::parse("custom", &input) {
+                Ok(custom) => {
+                    profile = custom;
+                    break;
+                }
+                Err(e) => println!("{e}"),
+            }
+        }
+    }
+
+    // Get the boss the user is preparing for, and how much status resistance they want.
+    let mut constraints: Vec<core::Constraint> = Vec::new();
+    if mode == Mode::Boss {
+        let bosses = core::bosses();
+        let boss = loop {
+            let input = prompt("Choose a boss by name: ");
+            match core::find_boss(&bosses, &input) {
+                Ok(boss) => break boss,
+                Err(candidates) if candidates.is_empty() => {
+                    println!("{input} doesn't match any boss");
+                }
+                Err(candidates) => {
+                    println!("{input} matches several bosses:");
+                    for candidate in candidates {
+                        println!("  {}", candidate.profile.name);
+                    }
+                }
+            }
+        };
+        println!("{boss}");
+        profile = boss.profile.clone();
+        if !boss.statuses.is_empty() {
+            loop {
+                let input =
+                    prompt("Choose minimum armor resistance to its statuses ([enter] for none): ");
+                if input.is_empty() {
+                    break;
+                }
+                let Ok(num) = input.parse::<f32>() else {
+                    println!("Couldn't parse input into a number. Try again!");
+                    continue;
+                };
+                constraints = boss.constraints((num * 10.0) as u16);
+                break;
+            }
+        }
+    }
+
+    // Get the buffs the user will have up during the fight.
+    let names: Vec<&str> = core::BUFFS.iter().map(|x| x.name).collect();
+    let buffs = loop {
+        let buffs = prompt_indexes(
+            "Choose indexes of negation buffs you'll have active ([enter] for none): ",
+            &names,
+            true,
+        );
+        match core::check_buffs(&buffs) {
+            Ok(()) => break buffs,
+            Err(e) => println!("{e}"),
+        }
+    };
+    let mut buffed_profile = profile.clone();
+    if matches!(mode, Mode::DamageMix | Mode::Boss)
+        && !buffs.is_empty()
+        && prompt("Choose armor for the damage the buffs let through? [y/N]: ").to_lowercase()
+            == "y"
+    {
+        buffed_profile = profile.with_buffs(&buffs);
+    }
+
+    // Get the character's level and attributes, so resistances can be shown and bounded as the
+    // game shows them.
+    let mut character: Option<core::Character> = None;
     loop {
-        let mut input = String::new();
-        for (index, key) in keys.iter().enumerate() {
-            println!("{index}: {key:#?}");
-        }
-        print!("Choose index of stat to maximize: ");
-        std::io::stdout().flush().expect("Failed to flush stdout");
-        std::io::stdin()
-            .read_line(&mut input)
-            .expect("Failed to get user input.");
-        maximize_stat = if let Ok(num) = input.trim().parse() {
-            num
-        } else {
-            println!("{input} is not an available stat");
+        let input = prompt("Choose rune level ([enter] to leave out character stats): ");
+        if input.is_empty() && mode == Mode::Survival {
+            println!("Damage taken depends on the character's defense, which needs their stats.");
+            continue;
+        }
+        if input.is_empty() {
+            break;
+        }
+        let Ok(level) = input.parse() else {
+            println!("Couldn't parse input into a level. Try again!");
             continue;
         };
+        if !(1..=core::MAX_LEVEL).contains(&level) {
+            println!("Rune levels go from 1 to {}.", core::MAX_LEVEL);
+            continue;
+        }
+        character = Some(core::Character {
+            level,
+            vigor: prompt_parse("Choose Vigor: "),
+            mind: prompt_parse("Choose Mind: "),
+            endurance: prompt_parse("Choose Endurance: "),
+            strength: prompt_parse("Choose Strength: "),
+            intelligence: prompt_parse("Choose Intelligence: "),
+            arcane: prompt_parse("Choose Arcane: "),
+        });
         break;
     }
 
+    // Get the attack to survive, and the HP to survive it with.
+    let mut attack = core::Attack { power: [0.0; 8] };
+    let mut hp: u16 = 0;
+    if mode == Mode::Survival {
+        loop {
+            let input = prompt("Choose the attack's power per type, like slash=300 fire=150: ");
+            match core::Attack::parse(&input) {
+                Ok(parsed) => {
+                    attack = parsed;
+                    break;
+                }
+                Err(e) => println!("{e}"),
+            }
+        }
+        hp = prompt_parse("Choose max HP: ");
+    }
+
+    // Get minimum in-game totals, like robustness=400. They're turned into armor minimums once
+    // the worn talismans are known.
+    let mut totals: Vec<core::Constraint> = Vec::new();
+    if constrained {
+        loop {
+            let input = prompt("Choose a minimum total like robustness=400 ([enter] to skip): ");
+            if input.is_empty() {
+                break;
+            }
+            match core::Constraint::parse(&input) {
+                Ok(total) => totals.push(total),
+                Err(e) => println!("{e}"),
+            }
+        }
+    }
+
+    // Get whether the character has Shadow of the Erdtree, unless the settings say.
+    let include_dlc = include_dlc.unwrap_or_else(|| {
+        prompt("Include Shadow of the Erdtree pieces? [Y/n]: ").to_lowercase() != "n"
+    });
+
     // Get keywords that should be ignored.
     let mut ignore_keywords: Vec<String> = Vec::new();
     loop {
-        let mut input = String::new();
-        print!("Choose a keyword to ignore ([enter] to skip): ");
-        std::io::stdout().flush().expect("Failed to flush stdout");
-        std::io::stdin()
-            .read_line(&mut input)
-            .expect("Failed to get user input.");
-        input = input.trim().to_lowercase().to_string();
+        let input = prompt("Choose a keyword to ignore ([enter] to skip): ").to_lowercase();
         match input.len() {
             0 => break,
             _ => ignore_keywords.push(input),
         }
     }
 
-    // Ignore unobtainable items.
-    ignore_keywords.push("grass hair ornament".into());
-    ignore_keywords.push("deathbed smalls".into());
-    ignore_keywords.push("millicent's".into());
-    ignore_keywords.push("brave's".into());
-    ignore_keywords.push("golden prosthetic".into());
-    ignore_keywords.push("ragged".into());
-
-    // Get the available weight.
-    let weight_restriction: f32;
+    // Get the available weight, either from Endurance and roll type or typed in directly.
+    let weight_restriction: u16;
+    let mut budget: Option<(u8, core::Roll, u16, Vec<usize>)> = None;
+    let mut owned: Vec<usize> = Vec::new();
+    let mut slots: usize = 0;
+    let mut planned: (u16, Vec<usize>) = (0, Vec::new());
+    let names: Vec<&str> = core::TALISMANS.iter().map(|x| x.name).collect();
     loop {
-        let mut input = String::new();
-        print!("Choose max armor weight: ");
-        std::io::stdout().flush().expect("Failed to flush stdout");
-        std::io::stdin()
-            .read_line(&mut input)
-            .expect("Failed to get user input.");
-        weight_restriction = if let Ok(num) = input.trim().parse() {
-            num
+        if mode == Mode::Endurance {
+            // The Endurance level is what gets planned, so only the rest of the load is needed.
+            let talismans = prompt_indexes(
+                "Choose indexes of these talismans you wear ([enter] for none): ",
+                &names,
+                true,
+            );
+            let equipped = prompt_weight("Choose weight of weapons and other equipped talismans: ");
+            weight_restriction = u16::MAX;
+            planned = (equipped, talismans);
+            break;
+        }
+        // The character's Endurance was already given with their other attributes.
+        let endurance = if let Some(character) = &character {
+            character.endurance
         } else {
-            println!("Couldn't parse input into a number. Try again!");
-            continue;
+            let input =
+                prompt("Choose Endurance level ([enter] to type max armor weight instead): ");
+            if input.is_empty() && mode == Mode::Talismans {
+                println!("Choosing talismans needs your Endurance level.");
+                continue;
+            }
+            if input.is_empty() {
+                weight_restriction = prompt_weight("Choose max armor weight: ");
+                break;
+            }
+            let Ok(endurance) = input.parse::<u8>() else {
+                println!("Couldn't parse input into a level. Try again!");
+                continue;
+            };
+            endurance
+        };
+        let roll = loop {
+            match prompt("Choose roll type (light, medium, heavy): ")
+                .to_lowercase()
+                .as_str()
+            {
+                "light" => break core::Roll::Light,
+                "medium" => break core::Roll::Medium,
+                "heavy" => break core::Roll::Heavy,
+                input => println!("{input} is not a roll type"),
+            }
+        };
+        if mode == Mode::Talismans {
+            owned = prompt_indexes(
+                "Choose indexes of these talismans you own ([enter] for all): ",
+                &names,
+                true,
+            );
+            if owned.is_empty() {
+                owned = (0..names.len()).collect();
+            }
+            loop {
+                match prompt("Choose number of talisman slots to fill: ").parse::<usize>() {
+                    Ok(num) if num <= 4 => {
+                        slots = num;
+                        break;
+                    }
+                    _ => println!("Choose a number from 0 to 4"),
+                }
+            }
+        }
+        let talismans = if mode == Mode::Talismans {
+            Vec::new()
+        } else {
+            prompt_indexes(
+                "Choose indexes of these talismans you wear ([enter] for none): ",
+                &names,
+                true,
+            )
         };
+        let equipped = prompt_weight("Choose weight of weapons and other equipped talismans: ");
+        weight_restriction = core::armor_budget(endurance, roll, equipped, &talismans);
+        println!(
+            "max equip load: {}, {roll} roll armor budget: {}",
+            f32::from(core::max_equip_load_with(endurance, &talismans)) / 10.0,
+            f32::from(weight_restriction) / 10.0
+        );
+        budget = Some((endurance, roll, equipped, talismans));
         break;
     }
-    let weight_restriction: u16 = (weight_restriction * 10.0) as u16;
 
-    // Get the pieces.
-    let mut pieces: Vec<core::ArmorPiece> = core::get_pieces(maximize_stat);
-    pieces.retain(|x| x.weight < weight_restriction);
+    // Get the enemy attack's poise damage, and how many hits of it to take without staggering.
+    let mut poise_damage: u16 = 0;
+    loop {
+        let input = prompt("Choose enemy attack's poise damage ([enter] to skip): ");
+        if input.is_empty() {
+            break;
+        }
+        match input.parse::<f32>() {
+            Ok(num) if num > 0.0 => poise_damage = (num * 10.0).round() as u16,
+            _ => {
+                println!("Couldn't parse input into a positive number. Try again!");
+                continue;
+            }
+        }
+        break;
+    }
+    let worn: Vec<usize> = budget.as_ref().map_or(planned.1.clone(), |x| x.3.clone());
+    for total in &totals {
+        let total = total.without_talismans(&worn);
+        constraints.push(match &character {
+            Some(character) => character.armor_constraint(&total),
+            None => total,
+        });
+    }
+    if poise_damage > 0 && constrained {
+        loop {
+            let input = prompt("Choose hits to take without stagger ([enter] for any): ");
+            if input.is_empty() {
+                break;
+            }
+            let Ok(hits) = input.parse() else {
+                println!("Couldn't parse input into a number. Try again!");
+                continue;
+            };
+            constraints.push(core::stagger_constraint(hits, poise_damage, &worn));
+            break;
+        }
+    }
+    // Get the enemy's status buildup per hit, and how many hits to take before each status procs.
+    let mut buildups: Vec<core::Buildup> = Vec::new();
+    loop {
+        let input = prompt("Choose enemy status buildup per hit like bleed=55 ([enter] to skip): ");
+        if input.is_empty() {
+            break;
+        }
+        let buildup = match core::Buildup::parse(&input) {
+            Ok(buildup) => buildup,
+            Err(e) => {
+                println!("{e}");
+                continue;
+            }
+        };
+        if constrained {
+            let message = format!(
+                "Choose hits to take before {} procs ([enter] for any): ",
+                core::STATUSES[buildup.status].0
+            );
+            loop {
+                let input = prompt(&message);
+                if input.is_empty() {
+                    break;
+                }
+                let Ok(hits) = input.parse() else {
+                    println!("Couldn't parse input into a number. Try again!");
+                    continue;
+                };
+                let total = buildup.constraint(hits).without_talismans(&worn);
+                constraints.push(match &character {
+                    Some(character) => character.armor_constraint(&total),
+                    None => total,
+                });
+                break;
+            }
+        }
+        buildups.push(buildup);
+    }
+    let extras = Extras {
+        buffs: &buffs,
+        character: character.as_ref(),
+        talismans: &worn,
+        poise_damage,
+        buildups: &buildups,
+    };
 
+    // Get the pieces.
+    let mut pieces: Vec<core::ArmorPiece> =
+        core::try_get_pieces_in(&cache_dir, maximize_stat, &urls, &fetcher)
+            .unwrap_or_else(|e| exit(e));
+    let overrides = core::load_overrides().unwrap_or_else(|e| exit(e));
+    let adjustments = core::adjust_pieces(
+        &mut pieces,
+        &overrides,
+        maximize_stat,
+        include_dlc,
+        include_unobtainable,
+    );
+    for message in adjustments.messages() {
+        println!("{message}");
+    }
     if !ignore_keywords.is_empty() {
         pieces.retain(|x| ignore_keywords.iter().all(|n| !x.name.contains(n)));
     }
+    let all_pieces = pieces.clone();
+    pieces.retain(|x| x.weight <= weight_restriction);
+
+    if mode == Mode::Talismans {
+        let (endurance, roll, equipped, _) = budget.expect("mode needs an Endurance level");
+        let found = core::get_set_with_talismans(
+            endurance,
+            roll,
+            equipped,
+            all_pieces,
+            maximize_stat,
+            &owned,
+            slots,
+        );
+        let (result, talismans) = match found {
+            Ok(Some(found)) => found,
+            Ok(None) => {
+                println!("\nNo set fits the equip load.");
+                return;
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        };
+        print_set(&result, &extras);
+        let names: Vec<&str> = talismans.iter().map(|x| core::TALISMANS[*x].name).collect();
+        println!("talismans:  {}", names.join(", "));
+        println!(
+            "armor budget with them: {}",
+            f32::from(core::armor_budget(endurance, roll, equipped, &talismans)) / 10.0
+        );
+        return;
+    }
+
+    if mode == Mode::Survival {
+        let character = extras.character.expect("mode needs character stats");
+        let result =
+            core::get_set_survival(weight_restriction, pieces, &attack, character, hp, &buffs);
+        print_set(&result, &extras);
+        let survival = |set: &core::ArmorSet| {
+            let set = set.with_buffs(&buffs);
+            format!(
+                "damage per hit: {:.0}, hits to die: {}",
+                attack.damage(&set, character),
+                attack.hits_to_die(&set, character, hp)
+            )
+        };
+        println!("{}", survival(&result));
+
+        // Compare against another set in HP terms.
+        loop {
+            let input = prompt(
+                "Choose a set to compare as helm, chest, gauntlets, legs ([enter] to skip): ",
+            );
+            if input.is_empty() {
+                break;
+            }
+            let names: Vec<&str> = input.split(',').collect();
+            let Ok(names) = <[&str; 4]>::try_from(names) else {
+                println!("Expected four names separated by commas, blank for an empty slot.");
+                continue;
+            };
+            match core::find_set(&all_pieces, names) {
+                Ok(other) => {
+                    println!(
+                        "\n{}",
+                        core::side_by_side(&result, &other, "best set:", "other set:")
+                    );
+                    println!("best set:  {}", survival(&result));
+                    println!("other set: {}", survival(&other));
+                }
+                Err(e) => println!("{e}"),
+            }
+        }
+        return;
+    }
 
-    let result = core::get_set(weight_restriction, pieces);
-    println!("\n{}", result);
+    if mode == Mode::Endurance {
+        let (equipped, talismans) = &planned;
+        let target = loop {
+            let input = prompt(
+                "Choose a set as helm, chest, gauntlets, legs ([enter] to plan for the minimums): ",
+            );
+            if input.is_empty() && constraints.is_empty() {
+                println!("Without minimums any Endurance level fits an empty set.");
+                continue;
+            }
+            if input.is_empty() {
+                break None;
+            }
+            let names: Vec<&str> = input.split(',').collect();
+            let Ok(names) = <[&str; 4]>::try_from(names) else {
+                println!("Expected four names separated by commas, blank for an empty slot.");
+                continue;
+            };
+            match core::find_set(&all_pieces, names) {
+                Ok(set) => break Some(set),
+                Err(e) => println!("{e}"),
+            }
+        };
+        if let Some(set) = &target {
+            print_set(set, &extras);
+        }
+        for roll in [core::Roll::Light, core::Roll::Medium, core::Roll::Heavy] {
+            let plan = match &target {
+                Some(set) => core::min_endurance(set.weight, roll, *equipped, talismans)
+                    .map(|endurance| (endurance, set.clone())),
+                None => core::min_endurance_constrained(
+                    &pieces,
+                    &constraints,
+                    roll,
+                    *equipped,
+                    talismans,
+                ),
+            };
+            let Some((endurance, set)) = plan else {
+                println!("\n{roll} roll: out of reach even at Endurance 99");
+                continue;
+            };
+            if target.is_none() {
+                print_set(&set, &extras);
+            }
+            let mut line = format!("{roll} roll: Endurance {endurance}");
+            if let Some(character) = extras.character {
+                let levels = endurance.saturating_sub(character.endurance);
+                if levels == 0 {
+                    line.push_str(", already there");
+                } else {
+                    line.push_str(&format!(
+                        ", {levels} more levels for {} runes",
+                        core::levels_cost(character.level, levels.into())
+                    ));
+                }
+            }
+            println!("{line}");
+        }
+        return;
+    }
+
+    if mode == Mode::Maximin {
+        let (result, binding) = core::get_set_maximin(weight_restriction, pieces, &group);
+        print_set(&result, &extras);
+        println!(
+            "binding minimum: {} ({})",
+            core::STATS[binding],
+            f32::from(result.stat(binding)) / 10.0
+        );
+        return;
+    }
+
+    if mode == Mode::Ratio {
+        match core::get_set_ratio(weight_restriction, pieces, min_stat) {
+            Some((result, ratio)) => {
+                print_set(&result, &extras);
+                println!(
+                    "{} per 10 weight: {:.2}",
+                    core::STATS[maximize_stat],
+                    ratio * 10.0 / f64::from(core::stat_divisor(maximize_stat))
+                );
+            }
+            None => println!("\nNo set within the weight reaches the minimum stat."),
+        }
+        return;
+    }
+
+    if mode == Mode::NearOptimal {
+        let (optimum, near) = core::get_set_near_optimal(weight_restriction, pieces, percent);
+        println!("\nstrict optimum:\n{}", optimum);
+        println!("lightest set within {percent}%:\n{near}");
+        println!(
+            "{} lost: {}\nweight saved: {}",
+            core::STATS[maximize_stat],
+            (f32::from(optimum.stat(maximize_stat)) - f32::from(near.stat(maximize_stat))) / 10.0,
+            f32::from(optimum.weight - near.weight) / 10.0
+        );
+        return;
+    }
+
+    if matches!(mode, Mode::DamageMix | Mode::Boss) {
+        match core::get_set_damage_profile(
+            weight_restriction,
+            pieces,
+            &buffed_profile,
+            &constraints,
+        ) {
+            Some(result) => {
+                print_set(&result, &extras);
+                println!(
+                    "damage taken from {}: {:.1}%",
+                    profile.name,
+                    profile.damage_taken(&result) * 100.0
+                );
+                if !buffs.is_empty() {
+                    println!(
+                        "damage taken with buffs: {:.1}%",
+                        profile.damage_taken(&result.with_buffs(&buffs)) * 100.0
+                    );
+                }
+            }
+            None => println!("\nNo set within the weight reaches the minimum resistance."),
+        }
+        return;
+    }
+
+    let result = if constraints.is_empty() {
+        Some(core::get_set(weight_restriction, pieces))
+    } else {
+        core::get_set_constrained(weight_restriction, pieces, &constraints)
+    };
+    let Some(result) = result else {
+        println!("\nNo set within the weight reaches the minimums.");
+        return;
+    };
+    print_set(&result, &extras);
+
+    // Show what changing one equip load talisman would do to the best set. The pruned search
+    // keeps this quick even with every piece in the game.
+    if let Some((endurance, roll, equipped, talismans)) = budget {
+        let mut changes: Vec<String> = Vec::new();
+        for (description, worn) in core::talisman_toggles(&talismans) {
+            let toggle_restriction = core::armor_budget(endurance, roll, equipped, &worn);
+            let Some(toggle_result) =
+                core::get_set_constrained(toggle_restriction, all_pieces.clone(), &constraints)
+            else {
+                changes.push(format!("{description}: no set reaches the minimums"));
+                continue;
+            };
+            changes.push(format!(
+                "{description}: armor budget {}, {} {} ({:+}), {} / {} / {} / {}",
+                f32::from(toggle_restriction) / 10.0,
+                core::STATS[maximize_stat],
+                f32::from(toggle_result.stat(maximize_stat)) / 10.0,
+                (f32::from(toggle_result.stat(maximize_stat))
+                    - f32::from(result.stat(maximize_stat)))
+                    / 10.0,
+                toggle_result.helm,
+                toggle_result.chest,
+                toggle_result.gauntlets,
+                toggle_result.legs,
+            ));
+        }
+        println!("\nchanging one equip load talisman:");
+        for change in changes {
+            println!("{change}");
+        }
+    }
 }